@@ -1,5 +1,10 @@
 /// Module with functions to build fltk widgets
 
+use std::{
+    cell::Cell,
+    rc::Rc
+};
+
 use fltk::{
     app::{
         App as FLTKApp,
@@ -21,6 +26,7 @@ use fltk::{
         FrameType
     },
     frame::Frame,
+    menu::Choice,
     group::{
         Pack,
         PackType
@@ -39,7 +45,8 @@ use fltk::{
         DisplayExt,
         ButtonExt,
         ValuatorExt,
-        ImageExt
+        ImageExt,
+        MenuExt
     },
     misc::Progress,
     valuator::Slider,
@@ -509,9 +516,11 @@ fn _build_4but_right_inner_pack() -> Pack {
 }
 
 /// Builds a pack of 4 buttons
+/// NOTE: if there's no data for the 2nd button, there'll be an empty space instead
 fn _build_4but_pack(
     sender: Sender<Message>,
     app_state: &ThreadSafeState,
+    but2_data: Option<(&str, Message)>,
     but3_data: (&str, Message)
 ) -> Pack {
     let mut outer_pack = _build_4but_outer_pack();
@@ -522,7 +531,14 @@ fn _build_4but_pack(
 
     build_button(BUT_ABORT_LABEL, sender, Message::Abort);
     // build_volume_but(sender, app_state);
-    _build_dummy_frame();
+    match but2_data {
+        Some((label, msg)) => {
+            build_button(label, sender, msg);
+        },
+        None => {
+            _build_dummy_frame();
+        }
+    };
 
     left_inner_pack.end();
 
@@ -548,6 +564,7 @@ fn _build_abort_back_contn_pack(sender: Sender<Message>, app_state: &ThreadSafeS
     _build_4but_pack(
         sender,
         app_state,
+        None,
         (BUT_CONTINUE_LABEL, Message::NextPage)
     );
 }
@@ -558,6 +575,18 @@ fn _build_abort_back_inst_pack(sender: Sender<Message>, app_state: &ThreadSafeSt
     _build_4but_pack(
         sender,
         app_state,
+        None,
+        (BUT_INSTALL_LABEL, Message::Install)
+    );
+}
+
+/// Builds a pack of 4 buttons
+/// Example: <Abort> <Preview>      <Back> <Install>
+fn _build_abort_preview_back_inst_pack(sender: Sender<Message>, app_state: &ThreadSafeState) {
+    _build_4but_pack(
+        sender,
+        app_state,
+        Some((BUT_PREVIEW_LABEL, Message::Preview)),
         (BUT_INSTALL_LABEL, Message::Install)
     );
 }
@@ -688,9 +717,81 @@ pub fn build_license_win(sender: Sender<Message>, app_state: &ThreadSafeState) -
 }
 
 
+/// Escapes the characters fltk treats specially in menu labels
+fn __escape_menu_label(label: &str) -> String {
+    let mut rv = String::new();
+
+    for c in label.chars() {
+        match c {
+            '/' | '\\' | '&' | '_' => {
+                rv.push('\\');
+                rv.push(c);
+            },
+            // Can't be escaped, it separates the items
+            '|' => {},
+            _ => rv.push(c)
+        };
+    }
+    return rv;
+}
+
+/// Builds a choice to pick the release to install
+/// NOTE: it has no items until you fill it
+pub fn build_version_choice(sender: Sender<Message>) -> Choice {
+    let mut choice = Choice::default()
+        .with_size(VERSION_CHOICE_WIDTH, VERSION_CHOICE_HEIGHT)
+        .with_pos(VERSION_CHOICE_XPOS, VERSION_CHOICE_YPOS)
+        .with_label(VERSION_CHOICE_LABEL);
+    choice.set_label_color(C_DDLC_PINK_DARK);
+    choice.set_label_font(BUT_FONT);
+    choice.set_label_size(BUT_FONT_SIZE);
+    choice.set_color(C_JN_SHADOW);
+    choice.set_text_color(C_WHITE);
+    choice.set_text_size(SEL_DIR_TXT_SIZE);
+    choice.set_selection_color(C_JN_PINK);
+    choice.emit(sender, Message::SelectRelease);
+
+    return choice;
+}
+
+/// Fills the version choice with the default entries and the given release tags
+/// Keeps the current selection
+pub fn fill_version_choice(choice: &mut Choice, tags: &[String]) {
+    let selected = choice.value();
+
+    choice.clear();
+    choice.add_choice(VERSION_CHOICE_LATEST_LABEL);
+    choice.add_choice(VERSION_CHOICE_PRERELEASE_LABEL);
+    for tag in tags {
+        choice.add_choice(&__escape_menu_label(tag));
+    }
+
+    choice.set_value(selected.max(0));
+}
+
+/// Builds a frame that tells the user about the existing install in the selected dir
+/// NOTE: it has no label until you set it
+pub fn build_install_status_frame() -> Frame {
+    let mut frame = Frame::default()
+        .with_size(INSTALL_STATUS_FRAME_WIDTH, INSTALL_STATUS_FRAME_HEIGHT)
+        .with_pos(INSTALL_STATUS_FRAME_XPOS, INSTALL_STATUS_FRAME_YPOS);
+    frame.set_align(Align::Center | Align::Inside | Align::Wrap);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_font(BUT_FONT);
+    frame.set_label_size(INSTALL_STATUS_FRAME_LABEL_SIZE);
+
+    return frame;
+}
+
 /// Builds the select directory window
-pub fn build_select_dir_win(sender: Sender<Message>, app_state: &ThreadSafeState, txt_buf: TextBuffer) -> DoubleWindow {
-    let select_dir_win = build_inner_win();
+pub fn build_select_dir_win(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    txt_buf: TextBuffer,
+    version_choice: &Choice,
+    install_status_frame: &Frame
+) -> DoubleWindow {
+    let mut select_dir_win = build_inner_win();
     select_dir_win.begin();
 
 
@@ -711,8 +812,20 @@ pub fn build_select_dir_win(sender: Sender<Message>, app_state: &ThreadSafeState
     let mut but = build_button(BUT_SELECT_DIR_LABEL, sender, Message::SelectDir);
     but.set_pos(INNER_WIN_CONTENT_XPADDING+SEL_DIR_TXT_WIDTH-BUT_WIDTH, SEL_DIR_TXT_YPOS+SEL_DIR_TXT_HEIGHT+BUT_SPACING/2);
 
+    let mut but = build_button(BUT_SELECT_ARCHIVES_LABEL, sender, Message::SelectArchives);
+    but.set_pos(INNER_WIN_CONTENT_XPADDING+SEL_DIR_TXT_WIDTH-2*BUT_WIDTH-BUT_SPACING, SEL_DIR_TXT_YPOS+SEL_DIR_TXT_HEIGHT+BUT_SPACING/2);
+
+    let mut but = build_button(BUT_CLEAR_CACHE_LABEL, sender, Message::ClearCache);
+    but.set_pos(INNER_WIN_CONTENT_XPADDING, SEL_DIR_TXT_YPOS+SEL_DIR_TXT_HEIGHT+BUT_SPACING/2);
+
+    let mut but = build_button(BUT_UNINSTALL_LABEL, sender, Message::Uninstall);
+    but.set_pos(INNER_WIN_CONTENT_XPADDING+BUT_WIDTH+BUT_SPACING, SEL_DIR_TXT_YPOS+SEL_DIR_TXT_HEIGHT+BUT_SPACING/2);
+
+    select_dir_win.add(version_choice);
+    select_dir_win.add(install_status_frame);
+
     // _build_abort_back_contn_pack(sender, app_state);
-    _build_abort_back_inst_pack(sender, app_state);
+    _build_abort_preview_back_inst_pack(sender, app_state);
 
 
     select_dir_win.end();
@@ -720,6 +833,31 @@ pub fn build_select_dir_win(sender: Sender<Message>, app_state: &ThreadSafeState
     return select_dir_win;
 }
 
+/// Builds the window that shows what the install would change
+/// NOTE: the text goes into the given buffer
+pub fn build_preview_win(sender: Sender<Message>, buf: TextBuffer) -> DoubleWindow {
+    let preview_win = build_inner_win();
+    preview_win.begin();
+
+    _build_top_frame(PREVIEW_FRAME_LABEL);
+
+    let mut txt_disp = TextDisplay::default()
+        .with_size(TXT_DISP_WIDTH, TXT_DISP_HEIGHT)
+        .with_pos(TXT_DISP_XPOS, TXT_DISP_YPOS);
+    txt_disp.wrap_mode(WrapMode::AtBounds, 0);
+    txt_disp.set_selection_color(C_JN_PINK);
+    txt_disp.set_buffer(buf);
+    txt_disp.set_color(C_JN_SHADOW);
+    txt_disp.set_text_color(C_WHITE);
+
+    let mut but = build_button(BUT_BACK_LABEL, sender, Message::ClosePreview);
+    but.set_pos(INNER_WIN_WIDTH-BUT_WIDTH-INNER_WIN_CONTENT_XPADDING, INNER_WIN_HEIGHT-BUT_HEIGHT-BUT_PACK_YPADDING);
+
+    preview_win.end();
+
+    return preview_win;
+}
+
 
 /// Builds the options window with various settings for installer
 pub fn build_options_win(sender: Sender<Message>, app_state: &ThreadSafeState, is_dlx_version: bool, install_spr: bool) -> DoubleWindow {
@@ -929,6 +1067,142 @@ pub fn build_msg_win(msg: &str) -> DoubleWindow {
 }
 
 
+/// Builds a button for the question window, it hides the window and records the answer
+fn _build_question_but(question_win: &DoubleWindow, label: &str, xpos: i32, answer: &Rc<Cell<bool>>, value: bool) -> Button {
+    let mut but = _build_button_base(
+        BUT_WIDTH,
+        BUT_HEIGHT,
+        label,
+        _handle_button,
+        _draw_button
+    );
+
+    but.set_pos(xpos, INNER_MSG_WIN_HEIGHT - BUT_HEIGHT - BUT_MSG_WIN_PADDING);
+    but.set_callback({
+        let mut win = question_win.clone();
+        let answer = answer.clone();
+        move |_| {
+            answer.set(value);
+            win.hide();
+        }
+    });
+
+    return but;
+}
+
+/// Builds a message box window with yes/no buttons, the answer is set when the user clicks one
+pub fn build_question_win(msg: &str, answer: &Rc<Cell<bool>>) -> DoubleWindow {
+    let (sw, sh) = screen_size();
+
+    let win_x = sw as i32/2 - MSG_WIN_WIDTH/2;
+    let win_y = sh as i32/2 - MSG_WIN_HEIGHT/2;
+
+    let mut question_win = Window::default()
+        .with_size(MSG_WIN_WIDTH, MSG_WIN_HEIGHT)
+        .with_pos(win_x, win_y)
+        .with_label(MSG_WIN_TITLE);
+    question_win.set_color(C_DDLC_PINK_IDLE);
+
+    let mut inner_win = Window::default()
+        .with_size(INNER_MSG_WIN_WIDTH, INNER_MSG_WIN_HEIGHT)
+        .with_pos(WIN_PADDING, WIN_PADDING);
+    inner_win.set_color(C_DDLC_WHITE_IDLE);
+
+
+    let mut frame = Frame::default()
+        .with_size(INNER_MSG_WIN_WIDTH, INNER_MSG_WIN_HEIGHT - BUT_HEIGHT - 2*BUT_MSG_WIN_PADDING)
+        .with_pos(0, 0)
+        .with_align(Align::Center | Align::Inside)
+        .with_label(msg);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_size(MSG_FRAME_LABEL_SIZE);
+
+
+    _build_question_but(&question_win, BUT_YES_LABEL, INNER_MSG_WIN_WIDTH/2 - BUT_WIDTH - BUT_SPACING/2, answer, true);
+    _build_question_but(&question_win, BUT_NO_LABEL, INNER_MSG_WIN_WIDTH/2 + BUT_SPACING/2, answer, false);
+
+
+    inner_win.end();
+
+    question_win.end();
+    question_win.hide();
+    question_win.make_modal(true);
+
+    return question_win;
+}
+
+
+/// Builds a button for the choice window, it sets the answer to the given index and closes the window
+fn _build_choice_but(choice_win: &DoubleWindow, label: &str, xpos: i32, answer: &Rc<Cell<Option<usize>>>, idx: usize) -> Button {
+    let mut but = _build_button_base(
+        BUT_WIDTH,
+        BUT_HEIGHT,
+        label,
+        _handle_button,
+        _draw_button
+    );
+
+    but.set_pos(xpos, INNER_MSG_WIN_HEIGHT - BUT_HEIGHT - BUT_MSG_WIN_PADDING);
+    but.set_callback({
+        let mut win = choice_win.clone();
+        let answer = answer.clone();
+        move |_| {
+            answer.set(Some(idx));
+            win.hide();
+        }
+    });
+
+    return but;
+}
+
+/// Builds a message box window with a button for each label, the index of the one the user clicks is set as the answer
+/// NOTE: up to 3 buttons fit into the window
+pub fn build_choice_win(msg: &str, labels: &[&str], answer: &Rc<Cell<Option<usize>>>) -> DoubleWindow {
+    let (sw, sh) = screen_size();
+
+    let win_x = sw as i32/2 - MSG_WIN_WIDTH/2;
+    let win_y = sh as i32/2 - MSG_WIN_HEIGHT/2;
+
+    let mut choice_win = Window::default()
+        .with_size(MSG_WIN_WIDTH, MSG_WIN_HEIGHT)
+        .with_pos(win_x, win_y)
+        .with_label(MSG_WIN_TITLE);
+    choice_win.set_color(C_DDLC_PINK_IDLE);
+
+    let mut inner_win = Window::default()
+        .with_size(INNER_MSG_WIN_WIDTH, INNER_MSG_WIN_HEIGHT)
+        .with_pos(WIN_PADDING, WIN_PADDING);
+    inner_win.set_color(C_DDLC_WHITE_IDLE);
+
+
+    let mut frame = Frame::default()
+        .with_size(INNER_MSG_WIN_WIDTH, INNER_MSG_WIN_HEIGHT - BUT_HEIGHT - 2*BUT_MSG_WIN_PADDING)
+        .with_pos(0, 0)
+        .with_align(Align::Center | Align::Inside)
+        .with_label(msg);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_size(MSG_FRAME_LABEL_SIZE);
+
+
+    // The buttons are centered
+    let total_width = labels.len() as i32 * (BUT_WIDTH + BUT_SPACING) - BUT_SPACING;
+    let mut xpos = INNER_MSG_WIN_WIDTH/2 - total_width/2;
+    for (idx, label) in labels.iter().enumerate() {
+        _build_choice_but(&choice_win, label, xpos, answer, idx);
+        xpos += BUT_WIDTH + BUT_SPACING;
+    }
+
+
+    inner_win.end();
+
+    choice_win.end();
+    choice_win.hide();
+    choice_win.make_modal(true);
+
+    return choice_win;
+}
+
+
 /// Builds a pack for the end screens
 fn _build_end_but_pack(sender: Sender<Message>) -> Pack {
 
@@ -966,6 +1240,20 @@ fn _build_exit_button(sender: Sender<Message>) -> Button {
     return but;
 }
 
+/// Builds a frame that tells the user where the replaced files have been backed up
+/// NOTE: it has no label until you set it
+pub fn build_backup_frame() -> Frame {
+    let mut frame = Frame::default()
+        .with_size(BACKUP_FRAME_WIDTH, BACKUP_FRAME_HEIGHT)
+        .with_pos(BACKUP_FRAME_XPOS, BACKUP_FRAME_YPOS);
+    frame.set_align(Align::Center | Align::Inside | Align::Wrap);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_font(BUT_FONT);
+    frame.set_label_size(BACKUP_FRAME_LABEL_SIZE);
+
+    return frame;
+}
+
 /// Builds the abort windows
 pub fn build_abort_win(sender: Sender<Message>) -> DoubleWindow {
     let abort_win = build_inner_win();
@@ -990,8 +1278,8 @@ pub fn build_abort_win(sender: Sender<Message>) -> DoubleWindow {
 }
 
 /// Builds the done windows
-pub fn build_done_win(sender: Sender<Message>) -> DoubleWindow {
-    let done_win = build_inner_win();
+pub fn build_done_win(sender: Sender<Message>, backup_frame: &Frame) -> DoubleWindow {
+    let mut done_win = build_inner_win();
     done_win.begin();
 
     _build_top_frame(DONE_TOP_FRAME_LABEL);
@@ -999,6 +1287,8 @@ pub fn build_done_win(sender: Sender<Message>) -> DoubleWindow {
 
     _build_end_but_pack(sender);
 
+    done_win.add(backup_frame);
+
     _build_exit_button(sender);
 
     done_win.end();
@@ -1,6 +1,10 @@
 /// The module that implements various dialogs
 
-use std::path::PathBuf;
+use std::{
+    cell::Cell,
+    path::PathBuf,
+    rc::Rc
+};
 
 use fltk::{
     app::wait,
@@ -36,6 +40,25 @@ pub fn run_select_dir_dlg(prompt: &str) -> PathBuf {
     return c.filename();
 }
 
+/// Launches select files dialog native to the target OS
+/// returns selected zip files, empty if the user cancelled
+pub fn run_select_archives_dlg(prompt: &str) -> Vec<PathBuf> {
+    let mut c = NativeFileChooser::new(NativeFileChooserType::BrowseMultiFile);
+
+    c.set_title(prompt);
+    c.set_filter("*.zip");
+
+    let cwd = utils::get_cwd();
+    match c.set_directory(&cwd) {
+        Err(err) => eprintln!("Failed to automatically set default dir: {err}"),
+        Ok(_) => {}
+    };
+
+    c.show();
+
+    return c.filenames();
+}
+
 /// Launches alert dialog
 /// NOTE: modal
 pub fn run_alert_dlg(msg: &str) {
@@ -61,3 +84,40 @@ pub fn run_msg_dlg(msg: &str) {
     }
     drop(win);
 }
+
+/// Launches question dialog with yes/no buttons
+/// returns true if the user has answered yes, closing the window means no
+/// NOTE: modal
+pub fn run_question_dlg(msg: &str) -> bool {
+    let answer = Rc::new(Cell::new(false));
+    let mut win = builder::build_question_win(
+        msg,
+        &answer
+    );
+    win.show();
+    while win.shown() {
+        wait();
+    }
+    drop(win);
+
+    return answer.get();
+}
+
+/// Launches dialog with a button for each label
+/// returns the index of the button the user has clicked, closing the window means None
+/// NOTE: modal
+pub fn run_choice_dlg(msg: &str, labels: &[&str]) -> Option<usize> {
+    let answer = Rc::new(Cell::new(None));
+    let mut win = builder::build_choice_win(
+        msg,
+        labels,
+        &answer
+    );
+    win.show();
+    while win.shown() {
+        wait();
+    }
+    drop(win);
+
+    return answer.get();
+}
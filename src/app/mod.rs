@@ -2,6 +2,7 @@
 
 pub mod builder;
 pub mod dialog;
+pub mod reporter;
 pub mod state;
 pub mod styles;
 
@@ -16,10 +17,13 @@ use fltk::{
         Receiver
     },
     text::TextBuffer,
+    frame::Frame,
+    menu::Choice,
     misc::Progress,
     prelude::{
         WidgetExt,
         GroupExt,
+        MenuExt,
     },
     window::DoubleWindow
 };
@@ -28,6 +32,11 @@ use webbrowser;
 use state::{ThreadSafeState, build_thread_safe_state};
 use super::{audio, errors, installer, utils};
 use errors::InstallError;
+use installer::{
+    release::{LocalArchives, ReleaseSelector},
+    uninstall::UninstallReport,
+    update::{self, ExistingInstall, UpdateStatus}
+};
 
 
 /// The message enum so different parts of the app can communicate
@@ -38,6 +47,11 @@ pub enum Message {
     NextPage,
     PrevPage,
     SelectDir,
+    SelectArchives,
+    ClearCache,
+    Uninstall,
+    SelectRelease,
+    ReleasesLoaded,
     DlxVersionCheck,
     InstallSprCheck,
     VolumeCheck,
@@ -47,10 +61,16 @@ pub enum Message {
     Extracting,
     DownloadingSpr,
     ExtractingSpr,
+    Installing,
     CleaningUp,
+    Preview,
+    PreviewLoaded,
+    ClosePreview,
     Error,
     Abort,
     Done,
+    Uninstalling,
+    UninstallDone,
     OpenCredits,
     OpenChangelog
 }
@@ -77,16 +97,23 @@ pub struct InstallerApp {
     // These windows need to be available directly
     abort_window: DoubleWindow,
     done_window: DoubleWindow,
+    preview_window: DoubleWindow,
 
     // Audio manager, option because audio might not work
     audio_manager: Option<audio::AudioManager>,
 
     // Handle to the installer thread, option because we might not start it/close early
     installer_th_handle: Option<thread::JoinHandle<installer::InstallResult>>,
+    // Handle to the uninstaller thread, it's only set while we're uninstalling
+    uninstaller_th_handle: Option<thread::JoinHandle<Result<UninstallReport, InstallError>>>,
 
     // These need to be updated
     path_txt_buf: TextBuffer,
-    progress_bar: Progress
+    progress_bar: Progress,
+    release_choice: Choice,
+    install_status_frame: Frame,
+    backup_frame: Frame,
+    preview_txt_buf: TextBuffer
 }
 
 impl InstallerApp {
@@ -97,7 +124,12 @@ impl InstallerApp {
         let state = build_thread_safe_state();
 
         let path_txt_buf = TextBuffer::default();
+        let preview_txt_buf = TextBuffer::default();
         let progress_bar = builder::build_progress_bar();
+        let mut release_choice = builder::build_version_choice(sender);
+        builder::fill_version_choice(&mut release_choice, &[]);
+        let install_status_frame = builder::build_install_status_frame();
+        let backup_frame = builder::build_backup_frame();
 
         let main_window = builder::build_outer_win(sender, &state);
         main_window.begin();
@@ -109,14 +141,15 @@ impl InstallerApp {
             [
                 builder::build_welcome_win(sender, &state),
                 builder::build_license_win(sender, &state),
-                builder::build_select_dir_win(sender, &state, path_txt_buf.clone()),
+                builder::build_select_dir_win(sender, &state, path_txt_buf.clone(), &release_choice, &install_status_frame),
                 // builder::build_options_win(sender, &state, is_dlx_version, install_spr),
                 builder::build_propgress_win(sender, &state, &progress_bar)
             ]
         };
 
         let abort_window = builder::build_abort_win(sender);
-        let done_window = builder::build_done_win(sender);
+        let done_window = builder::build_done_win(sender, &backup_frame);
+        let preview_window = builder::build_preview_win(sender, preview_txt_buf.clone());
 
         main_window.end();
 
@@ -138,13 +171,21 @@ impl InstallerApp {
             current_window_id: 0,
             abort_window,
             done_window,
+            preview_window,
             audio_manager,
             installer_th_handle: None,
+            uninstaller_th_handle: None,
             path_txt_buf,
-            progress_bar
+            progress_bar,
+            release_choice,
+            install_status_frame,
+            backup_frame,
+            preview_txt_buf
         };
         // Imprortant to set the dir again to update the text disp buffer
         installer.set_extraction_dir(utils::get_cwd());
+        // Fill the version picker in the background
+        load_releases_in_thread(installer.sender, &installer.state);
 
         return installer;
     }
@@ -161,8 +202,19 @@ impl InstallerApp {
                 match msg {
                     Message::UpdateProgressBar(val) => {
                         self.progress_bar.set_value(val);
+                        if self.uninstaller_th_handle.is_some() {
+                            let percent = (val * 100.0) as i32;
+                            self.install_status_frame.set_label(
+                                &format!("{} {percent}%", styles::INSTALL_STATUS_UNINSTALLING_LABEL)
+                            );
+                            self.redraw_current_window();
+                        }
                     },
                     Message::Close => {
+                        // Uninstalling can't be stopped halfway, so let it finish
+                        if let Some(th_handle) = self.uninstaller_th_handle.take() {
+                            th_handle.join().ok();
+                        }
                         break;
                     },
                     Message::NextPage => {
@@ -178,6 +230,62 @@ impl InstallerApp {
                         }
                         self.set_extraction_dir(selected_dir);
                     },
+                    Message::SelectArchives => {
+                        let paths = dialog::run_select_archives_dlg(styles::SEL_ARCHIVES_DLG_PROMPT);
+                        // Cancelling means we go back to downloading
+                        if paths.is_empty() {
+                            self.set_local_archives(None);
+                        }
+                        else {
+                            match LocalArchives::from_paths(&paths) {
+                                Ok(archives) => {
+                                    dialog::run_msg_dlg(styles::DLG_MSG_USING_LOCAL_ARCHIVES);
+                                    self.set_local_archives(Some(archives));
+                                },
+                                Err(e) => {
+                                    dialog::run_alert_dlg(&format!("{e}"));
+                                }
+                            };
+                        }
+                    },
+                    Message::ClearCache => {
+                        match installer::cache::clear_cache() {
+                            Ok(freed) => {
+                                let freed = freed as f64 / (1024.0*1024.0);
+                                dialog::run_msg_dlg(&format!("{}\n{freed:.1} MiB freed", styles::DLG_MSG_CACHE_CLEARED));
+                            },
+                            Err(e) => {
+                                dialog::run_alert_dlg(&format!("Failed to clear the cache: {e}"));
+                            }
+                        };
+                    },
+                    Message::Uninstall => {
+                        self.uninstall();
+                    },
+                    Message::SelectRelease => {
+                        let idx = self.release_choice.value();
+                        let mut app_state = self.state.lock().unwrap();
+                        let selector = match idx {
+                            0 => ReleaseSelector::Latest,
+                            1 => ReleaseSelector::LatestPrerelease,
+                            _ => {
+                                let tag_idx = (idx - styles::VERSION_CHOICE_FIXED_ITEMS) as usize;
+                                match app_state.get_available_releases().get(tag_idx) {
+                                    Some(tag) => ReleaseSelector::Tag(tag.clone()),
+                                    None => ReleaseSelector::Latest
+                                }
+                            }
+                        };
+                        println!("Selected release: {:?}", selector);
+                        app_state.set_release_selector(selector);
+                        drop(app_state);
+                        self.update_install_status();
+                    },
+                    Message::ReleasesLoaded => {
+                        let tags = self.state.lock().unwrap().get_available_releases().clone();
+                        builder::fill_version_choice(&mut self.release_choice, &tags);
+                        self.update_install_status();
+                    },
                     Message::DlxVersionCheck => {
                         let mut app_state = self.state.lock().unwrap();
                         app_state.invert_deluxe_ver_flag();
@@ -212,6 +320,10 @@ impl InstallerApp {
                         }
                     }
                     Message::Install => {
+                        // The user decides what happens to an existing install first
+                        if !self.choose_install_mode() {
+                            continue;
+                        }
                         let app_state = self.state.lock().unwrap();
                         // We warn the user again if the extraction dir looks wrong
                         if !utils::is_valid_ddlc_dir(app_state.get_extraction_dir()) {
@@ -225,9 +337,25 @@ impl InstallerApp {
                         self.cleanup_th_handle();
                         // Start a new thread
                         self.installer_th_handle = Some(
-                            installer::install_game_in_thread(self.sender, &self.state)
+                            reporter::install_game_in_thread(self.sender, &self.state)
                         );
                     },
+                    Message::Preview => {
+                        self.state.lock().unwrap().set_preview_text(None);
+                        self.preview_txt_buf.set_text(styles::PREVIEW_LOADING_MSG);
+                        self.hide_current_window();
+                        self.preview_window.show();
+                        reporter::preview_install_in_thread(self.sender, &self.state);
+                    },
+                    Message::PreviewLoaded => {
+                        if let Some(text) = self.state.lock().unwrap().get_preview_text() {
+                            self.preview_txt_buf.set_text(text);
+                        }
+                    },
+                    Message::ClosePreview => {
+                        self.preview_window.hide();
+                        self.show_current_window();
+                    },
                     Message::Preparing => {
                         println!("Preparing...");
                         self.progress_bar.set_label(styles::PB_LABEL_PREPARING);
@@ -248,6 +376,10 @@ impl InstallerApp {
                         println!("Done!\nExtracting spritepacks...");
                         self.progress_bar.set_label(styles::PB_LABEL_EXTRACTING_SPRITEPACKS);
                     },
+                    Message::Installing => {
+                        println!("Done!\nInstalling files...");
+                        self.progress_bar.set_label(styles::PB_LABEL_INSTALLING);
+                    },
                     Message::CleaningUp => {
                         println!("Done!\nCleaning up...");
                         self.progress_bar.set_label(styles::PB_LABEL_CLEANINGUP);
@@ -257,9 +389,29 @@ impl InstallerApp {
                         self.abort_installation();
                         let rv = self.cleanup_th_handle();
                         // Show the error if we can
-                        if let Some(e) = rv {
-                            dialog::run_alert_dlg(&format!("{e}"));
-                        }
+                        match rv {
+                            Some(InstallError::SignatureError(e)) if e.is_unsigned() => {
+                                if dialog::run_question_dlg(&format!("{}\n({e})", styles::DLG_MSG_UNSIGNED_RELEASE)) {
+                                    // Nothing has been changed yet, so we can just start over
+                                    let mut app_state = self.state.lock().unwrap();
+                                    app_state.set_abort_flag(false);
+                                    app_state.set_allow_unsigned_flag(true);
+                                    drop(app_state);
+                                    self.installer_th_handle = Some(
+                                        reporter::install_game_in_thread(self.sender, &self.state)
+                                    );
+                                    continue;
+                                }
+                                dialog::run_alert_dlg(&format!("{}\n{e}", styles::DLG_MSG_UNTRUSTED_RELEASE));
+                            },
+                            Some(InstallError::SignatureError(e)) => {
+                                dialog::run_alert_dlg(&format!("{}\n{e}", styles::DLG_MSG_UNTRUSTED_RELEASE));
+                            },
+                            Some(e) => {
+                                dialog::run_alert_dlg(&format!("{e}"));
+                            },
+                            None => {}
+                        };
                         // Let's just quit
                         self.sender.send(Message::Close);
                     },
@@ -273,9 +425,22 @@ impl InstallerApp {
                     Message::Done => {
                         println!("Done!\nInstallation is complete!");
                         self.abort_installation();
+                        if let Some(backup_dir) = self.state.lock().unwrap().get_backup_dir() {
+                            self.backup_frame.set_label(
+                                &format!("{}\n{}", styles::BACKUP_FRAME_LABEL, backup_dir.display())
+                            );
+                        }
                         self.hide_current_window();
                         self.done_window.show();
                     },
+                    Message::Uninstalling => {
+                        println!("Uninstalling...");
+                        self.install_status_frame.set_label(styles::INSTALL_STATUS_UNINSTALLING_LABEL);
+                        self.redraw_current_window();
+                    },
+                    Message::UninstallDone => {
+                        self.finish_uninstall();
+                    },
                     Message::OpenCredits => {
                         if let Err(e) = webbrowser::open(crate::CREDITS_URL) {
                             eprintln!("Failed to open browser {e}");
@@ -361,16 +526,220 @@ impl InstallerApp {
         app_state.set_abort_flag(true);
     }
 
+    /// Updates the archives to install from, the version picker is disabled while they're set
+    fn set_local_archives(&mut self, archives: Option<LocalArchives>) {
+        match archives {
+            Some(_) => self.release_choice.deactivate(),
+            None => self.release_choice.activate()
+        };
+        self.state.lock().unwrap().set_local_archives(archives);
+        self.update_install_status();
+    }
+
     /// Updates the extraction dir and text display with the path
     fn set_extraction_dir(&mut self, new_dir: PathBuf) {
         if new_dir.is_dir() && new_dir.parent().is_some() {
             let mut app_state = self.state.lock().unwrap();
             app_state.set_extraction_dir(new_dir);
             self.path_txt_buf.set_text(app_state.get_extraction_dir_str());
+            drop(app_state);
+            self.update_install_status();
+        }
+    }
+
+    /// Asks the user to confirm and uninstalls from the selected dir
+    fn uninstall(&mut self) {
+        let extraction_dir = self.state.lock().unwrap().get_extraction_dir().clone();
+        let install = match update::detect_install(&extraction_dir) {
+            Some(install) => install,
+            None => {
+                dialog::run_msg_dlg(styles::DLG_MSG_NOT_INSTALLED);
+                return;
+            }
+        };
+        if !dialog::run_question_dlg(styles::DLG_MSG_CONFIRM_UNINSTALL) {
+            return;
+        }
+        let remove_spr = install.has_spritepacks
+            && dialog::run_question_dlg(styles::DLG_MSG_UNINSTALL_SPRITEPACKS);
+
+        // The window stays inactive until the uninstall is done
+        self.linked_windows[self.current_window_id].deactivate();
+        self.uninstaller_th_handle = Some(
+            reporter::uninstall_in_thread(self.sender, &self.state, extraction_dir, remove_spr)
+        );
+    }
+
+    /// Joins the uninstaller thread and tells the user how the uninstall went
+    fn finish_uninstall(&mut self) {
+        let th_handle = match self.uninstaller_th_handle.take() {
+            Some(th_handle) => th_handle,
+            None => return
+        };
+        self.linked_windows[self.current_window_id].activate();
+        let rv = match th_handle.join() {
+            Ok(rv) => rv,
+            Err(rv) => {
+                eprintln!("Failed to join uninstaller thread {:?}", rv);
+                dialog::run_msg_dlg(styles::DLG_MSG_UNINSTALL_INCOMPLETE);
+                self.update_install_status();
+                return;
+            }
+        };
+
+        match rv {
+            Ok(report) if report.failures.is_empty() => {
+                println!("Removed {} files, restored {} files", report.removed_files, report.restored_files);
+                dialog::run_msg_dlg(styles::DLG_MSG_UNINSTALLED);
+            },
+            Ok(report) => {
+                dialog::run_alert_dlg(
+                    &format!("{}\n{}", styles::DLG_MSG_UNINSTALL_INCOMPLETE, report.failures.join("\n"))
+                );
+            },
+            Err(e) => {
+                dialog::run_alert_dlg(&format!("Failed to uninstall: {e}"));
+            }
+        };
+        self.update_install_status();
+    }
+
+    /// Asks the user whether to update or reinstall the install in the selected dir (if there's one)
+    /// Returns false if the user has cancelled, reinstalling the installed version selects its release
+    fn choose_install_mode(&mut self) -> bool {
+        let app_state = self.state.lock().unwrap();
+        let install = match update::detect_install(app_state.get_extraction_dir()) {
+            Some(install) => install,
+            None => return true
+        };
+        // We can't tell the version of local archives
+        let release_tag = match app_state.get_local_archives() {
+            Some(_) => None,
+            None => app_state.get_selected_release_tag().cloned()
+        };
+        // The installed version can only be reinstalled if its release is still available
+        let installed_tag = install.version.clone()
+            .filter(|version| app_state.get_available_releases().contains(version));
+        // The installed spritepacks are kept up to date, unless the user gives us the archives
+        let updates_spr = install.has_spritepacks && app_state.get_local_archives().is_none();
+        drop(app_state);
+
+        let installed = match install.version {
+            Some(ref version) => format!("Just Natsuki {version}"),
+            None => "Just Natsuki".to_string()
+        };
+        let status = match release_tag {
+            Some(ref release_tag) => update::get_update_status(Some(&install), release_tag),
+            None => UpdateStatus::UnknownVersion
+        };
+        let release_tag = release_tag.unwrap_or_default();
+
+        let (mut msg, main_label) = match status {
+            UpdateStatus::UpToDate => {
+                (format!("{installed} is already installed\nand up to date"), styles::BUT_REINSTALL_LABEL)
+            },
+            UpdateStatus::UpdateAvailable => {
+                (format!("{installed} is installed,\nupdate it to {release_tag}?"), styles::BUT_UPDATE_LABEL)
+            },
+            UpdateStatus::NewerInstalled => {
+                (format!("{installed} is installed,\nit's newer than {release_tag}"), styles::BUT_DOWNGRADE_LABEL)
+            },
+            _ => {
+                (format!("{installed} is already installed,\ninstalling will replace it"), styles::BUT_REPLACE_LABEL)
+            }
+        };
+        if updates_spr {
+            msg = format!("{msg}\n{}", styles::DLG_MSG_UPDATING_SPRITEPACKS);
         }
+        let can_reinstall_installed = installed_tag.is_some()
+            && matches!(status, UpdateStatus::UpdateAvailable | UpdateStatus::NewerInstalled);
+        let labels: Vec<&str> = match can_reinstall_installed {
+            true => vec![main_label, styles::BUT_REINSTALL_LABEL, styles::BUT_CANCEL_LABEL],
+            false => vec![main_label, styles::BUT_CANCEL_LABEL]
+        };
+
+        return match dialog::run_choice_dlg(&msg, &labels) {
+            Some(0) => true,
+            Some(1) if can_reinstall_installed => {
+                let tag = installed_tag.unwrap();
+                println!("Reinstalling the installed release: {tag}");
+                let tags = self.state.lock().unwrap().get_available_releases().clone();
+                if let Some(idx) = tags.iter().position(|t| *t == tag) {
+                    self.release_choice.set_value(idx as i32 + styles::VERSION_CHOICE_FIXED_ITEMS);
+                }
+                self.state.lock().unwrap().set_release_selector(ReleaseSelector::Tag(tag));
+                true
+            },
+            _ => false
+        };
+    }
+
+    /// Tells the user what installing would do to the install in the selected dir
+    fn update_install_status(&mut self) {
+        let app_state = self.state.lock().unwrap();
+        let install = update::detect_install(app_state.get_extraction_dir());
+        // We can't tell the version of local archives
+        let release_tag = match app_state.get_local_archives() {
+            Some(_) => None,
+            None => app_state.get_selected_release_tag().cloned()
+        };
+        drop(app_state);
+
+        self.install_status_frame.set_label(&format_install_status(install.as_ref(), release_tag.as_deref()));
+        self.redraw_current_window();
     }
 }
 
+/// Returns the text about the existing install for the select dir window
+fn format_install_status(install: Option<&ExistingInstall>, release_tag: Option<&str>) -> String {
+    let install = match install {
+        Some(install) => install,
+        None => return String::new()
+    };
+    let installed = match install.version {
+        Some(ref version) => format!("Just Natsuki {version}"),
+        None => "Just Natsuki".to_string()
+    };
+    let release_tag = match release_tag {
+        Some(tag) => tag,
+        None => return format!("{installed} is already installed,\ninstalling will replace it")
+    };
+
+    return match update::get_update_status(Some(install), release_tag) {
+        UpdateStatus::UpToDate => format!("{installed} is already installed and up to date,\ninstalling will reinstall it"),
+        UpdateStatus::NewerInstalled => format!("{installed} is installed, it's newer than {release_tag},\ninstalling will downgrade it"),
+        _ => format!("{installed} is installed,\ninstalling will update it to {release_tag}")
+    };
+}
+
+/// Loads the list of releases in a separate thread so we don't block the GUI
+/// Sends ReleasesLoaded once the list is in the app state
+fn load_releases_in_thread(sender: Sender<Message>, app_state: &ThreadSafeState) {
+    let app_state = app_state.clone();
+    let settings = app_state.lock().unwrap().get_settings().clone();
+
+    thread::spawn(
+        move || {
+            match installer::get_available_releases(&settings) {
+                Ok(releases) => {
+                    let latest_release = releases.iter()
+                        .find(|data| !data.is_prerelease)
+                        .map(|data| data.version.clone());
+                    let tags = releases.into_iter().map(|data| data.version).collect();
+                    let mut app_state = app_state.lock().unwrap();
+                    app_state.set_available_releases(tags);
+                    app_state.set_latest_release(latest_release);
+                    drop(app_state);
+                    sender.send(Message::ReleasesLoaded);
+                },
+                Err(e) => {
+                    eprintln!("Failed to load the list of releases: {e}");
+                }
+            };
+        }
+    );
+}
+
 impl Default for InstallerApp {
     fn default() -> Self {
         return Self::new();
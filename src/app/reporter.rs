@@ -0,0 +1,147 @@
+/// The module that connects the installer to the GUI
+
+use std::{
+    path::{Path, PathBuf},
+    thread
+};
+
+use fltk::app::Sender;
+
+use crate::{
+    errors::InstallError,
+    installer::{
+        self,
+        dry_run,
+        InstallOptions,
+        InstallResult,
+        reporter::{InstallEvent, Reporter},
+        uninstall::{self, UninstallReport},
+        update
+    }
+};
+use super::{state::ThreadSafeState, styles::PREVIEW_FAILED_MSG, Message};
+
+
+/// Reporter that forwards installer events thru the fltk channel
+pub struct GuiReporter {
+    sender: Sender<Message>,
+    app_state: ThreadSafeState
+}
+
+impl GuiReporter {
+    /// Creates a new GuiReporter
+    pub fn new(sender: Sender<Message>, app_state: ThreadSafeState) -> Self {
+        return Self { sender, app_state };
+    }
+}
+
+impl Reporter for GuiReporter {
+    fn report(&self, event: InstallEvent) {
+        let msg = match event {
+            InstallEvent::Progress(val) => Message::UpdateProgressBar(val),
+            InstallEvent::Preparing => Message::Preparing,
+            InstallEvent::Downloading => Message::Downloading,
+            InstallEvent::Extracting => Message::Extracting,
+            InstallEvent::DownloadingSpr => Message::DownloadingSpr,
+            InstallEvent::ExtractingSpr => Message::ExtractingSpr,
+            InstallEvent::Installing => Message::Installing,
+            InstallEvent::CleaningUp => Message::CleaningUp,
+            InstallEvent::Done => Message::Done,
+            InstallEvent::Uninstalling => Message::Uninstalling
+        };
+        self.sender.send(msg);
+    }
+
+    fn report_backup(&self, path: &Path) {
+        self.app_state.lock().unwrap().set_backup_dir(Some(path.to_path_buf()));
+    }
+
+    fn is_aborted(&self) -> bool {
+        return self.app_state.lock().unwrap().get_abort_flag();
+    }
+}
+
+
+/// Builds the install options from the app state
+fn build_install_options(app_state: &ThreadSafeState) -> InstallOptions {
+    let app_state = app_state.lock().unwrap();
+    let mut options = InstallOptions::new(
+        app_state.get_extraction_dir().clone(),
+        app_state.get_install_spr_flag(),
+        app_state.get_release_selector().clone(),
+        app_state.get_settings().clone()
+    );
+    options.allow_unsigned = app_state.get_allow_unsigned_flag();
+    if let Some(archives) = app_state.get_local_archives() {
+        // Selecting the spritepacks means you want them
+        options.install_spr |= archives.spritepacks.is_some();
+        options.local_archives = Some(archives.clone());
+    }
+    // Updating an install keeps the spritepacks up to date too, the install mode dialog says so
+    else if let Some(install) = update::detect_install(&options.extraction_dir) {
+        options.install_spr |= install.has_spritepacks;
+    }
+
+    return options;
+}
+
+/// Threaded version of install_game, takes options from the app state
+pub fn install_game_in_thread(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState
+) -> thread::JoinHandle<InstallResult> {
+
+    let options = build_install_options(app_state);
+    let reporter = GuiReporter::new(sender, app_state.clone());
+
+    return thread::spawn(
+        move || -> InstallResult {
+            return match installer::install_game(&options, &reporter) {
+                Err(e) => {
+                    sender.send(Message::Error);
+                    Err(e)
+                },
+                Ok(_) => Ok(())
+            };
+        }
+    );
+}
+
+/// Threaded version of uninstall, sends UninstallDone once it's finished
+pub fn uninstall_in_thread(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    extraction_dir: PathBuf,
+    remove_spr: bool
+) -> thread::JoinHandle<Result<UninstallReport, InstallError>> {
+    let reporter = GuiReporter::new(sender, app_state.clone());
+
+    return thread::spawn(
+        move || -> Result<UninstallReport, InstallError> {
+            let rv = uninstall::uninstall(&reporter, &extraction_dir, remove_spr);
+            sender.send(Message::UninstallDone);
+            return rv;
+        }
+    );
+}
+
+/// Finds out what the install would change in a thread, the text is put into the app state
+/// Sends PreviewLoaded when it's there
+pub fn preview_install_in_thread(sender: Sender<Message>, app_state: &ThreadSafeState) {
+    let options = build_install_options(app_state);
+    let app_state = app_state.clone();
+
+    thread::spawn(
+        move || {
+            let text = match dry_run::plan_install(&options) {
+                Ok(plan) => plan.to_string(),
+                Err(e) => {
+                    eprintln!("Failed to preview the install: {e}");
+                    format!("{PREVIEW_FAILED_MSG}\n{e}")
+                }
+            };
+            app_state.lock().unwrap().set_preview_text(Some(text));
+            sender.send(Message::PreviewLoaded);
+        }
+    );
+}
@@ -7,7 +7,11 @@ use std::{
         Mutex
     },
 };
-use crate::audio::Volume;
+use crate::{
+    audio::Volume,
+    installer::release::{LocalArchives, ReleaseSelector},
+    settings::Settings
+};
 
 
 pub type ThreadSafeState = Arc<Mutex<AppState>>;
@@ -20,7 +24,20 @@ pub struct AppState {
     abort_flag: bool,
     deluxe_ver_flag: bool,
     install_spr_flag: bool,
-    music_volume: Volume
+    // Whether the user has agreed to install a release without a signature
+    allow_unsigned_flag: bool,
+    music_volume: Volume,
+    settings: Settings,
+    release_selector: ReleaseSelector,
+    // Tags of the releases the user can pick from
+    available_releases: Vec<String>,
+    // Tag of the latest stable release, None until the list is loaded
+    latest_release: Option<String>,
+    local_archives: Option<LocalArchives>,
+    // Where the last install has backed up the files it replaced
+    backup_dir: Option<PathBuf>,
+    // What the install would change, None while it's being found out
+    preview_text: Option<String>
 }
 
 #[allow(dead_code)]
@@ -31,14 +48,23 @@ impl AppState {
         abort_flag: bool,
         deluxe_ver_flag: bool,
         install_spr_flag: bool,
-        music_volume: Volume
+        music_volume: Volume,
+        settings: Settings
     ) -> Self {
         return Self {
             extraction_dir,
             abort_flag,
             deluxe_ver_flag,
             install_spr_flag,
-            music_volume
+            allow_unsigned_flag: false,
+            music_volume,
+            settings,
+            release_selector: ReleaseSelector::default(),
+            available_releases: Vec::new(),
+            latest_release: None,
+            local_archives: None,
+            backup_dir: None,
+            preview_text: None
         };
     }
 
@@ -106,6 +132,91 @@ impl AppState {
     pub fn set_music_volume(&mut self, value: Volume) {
         self.music_volume = value;
     }
+
+    /// Returns the installer settings
+    pub fn get_settings(&self) -> &Settings {
+        return &self.settings;
+    }
+
+    /// Returns the selected release
+    pub fn get_release_selector(&self) -> &ReleaseSelector {
+        return &self.release_selector;
+    }
+
+    /// Sets the selected release
+    pub fn set_release_selector(&mut self, value: ReleaseSelector) {
+        self.release_selector = value;
+    }
+
+    /// Returns the tags of the releases the user can pick from
+    pub fn get_available_releases(&self) -> &Vec<String> {
+        return &self.available_releases;
+    }
+
+    /// Sets the tags of the releases the user can pick from
+    pub fn set_available_releases(&mut self, value: Vec<String>) {
+        self.available_releases = value;
+    }
+
+    /// Returns the tag of the latest stable release
+    pub fn get_latest_release(&self) -> &Option<String> {
+        return &self.latest_release;
+    }
+
+    /// Sets the tag of the latest stable release
+    pub fn set_latest_release(&mut self, value: Option<String>) {
+        self.latest_release = value;
+    }
+
+    /// Returns the tag of the release the selector points to, None if we don't know it yet
+    pub fn get_selected_release_tag(&self) -> Option<&String> {
+        return match self.release_selector {
+            ReleaseSelector::Latest => self.latest_release.as_ref(),
+            // The list is sorted newest first
+            ReleaseSelector::LatestPrerelease => self.available_releases.first(),
+            ReleaseSelector::Tag(ref tag) => Some(tag)
+        };
+    }
+
+    /// Returns the archives to install instead of downloading
+    pub fn get_local_archives(&self) -> &Option<LocalArchives> {
+        return &self.local_archives;
+    }
+
+    /// Sets the archives to install instead of downloading
+    pub fn set_local_archives(&mut self, value: Option<LocalArchives>) {
+        self.local_archives = value;
+    }
+
+    /// Returns the flag that allows installing unsigned releases
+    pub fn get_allow_unsigned_flag(&self) -> bool {
+        return self.allow_unsigned_flag;
+    }
+
+    /// Sets the flag that allows installing unsigned releases
+    pub fn set_allow_unsigned_flag(&mut self, value: bool) {
+        self.allow_unsigned_flag = value;
+    }
+
+    /// Returns the dir with the files the last install has replaced
+    pub fn get_backup_dir(&self) -> &Option<PathBuf> {
+        return &self.backup_dir;
+    }
+
+    /// Sets the dir with the files the last install has replaced
+    pub fn set_backup_dir(&mut self, value: Option<PathBuf>) {
+        self.backup_dir = value;
+    }
+
+    /// Returns the text that tells what the install would change
+    pub fn get_preview_text(&self) -> &Option<String> {
+        return &self.preview_text;
+    }
+
+    /// Sets the text that tells what the install would change
+    pub fn set_preview_text(&mut self, value: Option<String>) {
+        self.preview_text = value;
+    }
 }
 
 impl Default for AppState {
@@ -115,7 +226,8 @@ impl Default for AppState {
             false,
             true,
             false,
-            1.0
+            1.0,
+            Settings::load()
         );
     }
 }
@@ -74,10 +74,21 @@ pub const BUT_ABORT_LABEL: &str = "Abort";
 pub const BUT_BACK_LABEL: &str = "@< Back ";
 pub const BUT_CONTINUE_LABEL: &str = " Continue@>";
 pub const BUT_SELECT_DIR_LABEL: &str = "Browse @fileopen";
+pub const BUT_SELECT_ARCHIVES_LABEL: &str = "From files @filenew";
+pub const BUT_CLEAR_CACHE_LABEL: &str = "Clear cache";
+pub const BUT_UNINSTALL_LABEL: &str = "Uninstall";
+pub const BUT_PREVIEW_LABEL: &str = "Preview";
 // pub const BUT_DLX_VER_CHECK_LABEL: &str = "Deluxe version (pre-installed spritepacks)";
 pub const BUT_INSTALL_SPR_CHECK_LABEL: &str = "(No additional options available)";
 pub const BUT_INSTALL_LABEL: &str = "Install";
 pub const BUT_OK_LABEL: &str = "Ok";
+pub const BUT_YES_LABEL: &str = "Yes";
+pub const BUT_NO_LABEL: &str = "No";
+pub const BUT_UPDATE_LABEL: &str = "Update";
+pub const BUT_REINSTALL_LABEL: &str = "Reinstall";
+pub const BUT_DOWNGRADE_LABEL: &str = "Downgrade";
+pub const BUT_REPLACE_LABEL: &str = "Replace";
+pub const BUT_CANCEL_LABEL: &str = "Cancel";
 pub const BUT_EXIT_LABEL: &str = "Exit";
 pub const BUT_CREDITS_LABEL: &str = "MyNewSoundtrack\nYouTube channel";
 pub const BUT_CHANGELOG_LABEL: &str = "Changelog";
@@ -123,7 +134,32 @@ pub const SEL_DIR_TXT_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDIN
 pub const SEL_DIR_TXT_HEIGHT: i32 = 28;
 pub const SEL_DIR_TXT_SIZE: i32 = 18;
 
+pub const VERSION_CHOICE_LABEL: &str = "Version ";
+pub const VERSION_CHOICE_LABEL_WIDTH: i32 = 80;
+pub const VERSION_CHOICE_XPOS: i32 = SEL_DIR_TXT_XPOS + VERSION_CHOICE_LABEL_WIDTH;
+pub const VERSION_CHOICE_YPOS: i32 = SEL_DIR_TXT_YPOS + SEL_DIR_TXT_HEIGHT + BUT_HEIGHT + 2*BUT_SPACING;
+pub const VERSION_CHOICE_WIDTH: i32 = SEL_DIR_TXT_WIDTH - VERSION_CHOICE_LABEL_WIDTH;
+pub const VERSION_CHOICE_HEIGHT: i32 = SEL_DIR_TXT_HEIGHT;
+pub const VERSION_CHOICE_LATEST_LABEL: &str = "Latest release";
+pub const VERSION_CHOICE_PRERELEASE_LABEL: &str = "Latest pre-release (beta)";
+// The number of entries before the release tags
+pub const VERSION_CHOICE_FIXED_ITEMS: i32 = 2;
+
+pub const INSTALL_STATUS_FRAME_XPOS: i32 = SEL_DIR_TXT_XPOS;
+pub const INSTALL_STATUS_FRAME_YPOS: i32 = VERSION_CHOICE_YPOS + VERSION_CHOICE_HEIGHT + BUT_SPACING;
+pub const INSTALL_STATUS_FRAME_WIDTH: i32 = SEL_DIR_TXT_WIDTH;
+pub const INSTALL_STATUS_FRAME_HEIGHT: i32 = 2*SEL_DIR_TXT_HEIGHT;
+pub const INSTALL_STATUS_FRAME_LABEL_SIZE: i32 = 16;
+pub const INSTALL_STATUS_UNINSTALLING_LABEL: &str = "Uninstalling...";
+
+pub const BACKUP_FRAME_XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
+pub const BACKUP_FRAME_HEIGHT: i32 = 50;
+pub const BACKUP_FRAME_YPOS: i32 = INNER_WIN_HEIGHT - BUT_HEIGHT - BUT_PACK_YPADDING - BACKUP_FRAME_HEIGHT - BUT_SPACING;
+pub const BACKUP_FRAME_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDING;
+pub const BACKUP_FRAME_LABEL_SIZE: i32 = 14;
+
 pub const SEL_DIR_DLG_PROMPT: &str = "Select Doki Doki Literature Club directory";
+pub const SEL_ARCHIVES_DLG_PROMPT: &str = "Select downloaded Just Natsuki archives";
 
 pub const LABEL_SIZE_LARGE: i32 = 28;
 pub const LABEL_SIZE_MED: i32 = 20;
@@ -132,6 +168,16 @@ pub const LABEL_SIZE_MED: i32 = 20;
 // Dialogue consts
 pub const DLG_MSG_SELECTED_BAD_DIR: &str = "Attention!\nSelected directory doesn't appear to be\na valid DDLC directory";
 pub const DLG_MSG_INSTALLING_IN_BAD_DIR: &str = "Attention!\nInstalling into a non-DDLC directory";
+pub const DLG_MSG_UNTRUSTED_RELEASE: &str = "The installation has been refused!\nThis release couldn't be verified as published by the Just Natsuki Team,\nnothing has been installed.";
+pub const DLG_MSG_UNSIGNED_RELEASE: &str = "This release isn't signed by the Just Natsuki Team,\nso it can't be verified it hasn't been tampered with.\nInstall it anyway?";
+pub const DLG_MSG_NOT_INSTALLED: &str = "Just Natsuki isn't installed\nin the selected directory";
+pub const DLG_MSG_CONFIRM_UNINSTALL: &str = "Uninstall Just Natsuki?\nThe files it has replaced will be restored";
+pub const DLG_MSG_UPDATING_SPRITEPACKS: &str = "(the installed spritepacks will be updated too)";
+pub const DLG_MSG_UNINSTALL_SPRITEPACKS: &str = "Also delete the spritepacks folder?";
+pub const DLG_MSG_UNINSTALLED: &str = "Just Natsuki has been uninstalled";
+pub const DLG_MSG_UNINSTALL_INCOMPLETE: &str = "Just Natsuki has been partially uninstalled,\nthese files couldn't be removed or restored:";
+pub const DLG_MSG_CACHE_CLEARED: &str = "The cached releases and downloads\n(partial and finished) have been deleted";
+pub const DLG_MSG_USING_LOCAL_ARCHIVES: &str = "The selected archives will be installed\ninstead of downloading the release";
 
 
 // Progress bar consts
@@ -144,6 +190,7 @@ pub const PB_LABEL_DOWNLOADING_GAME: &str = "Downloading...";
 pub const PB_LABEL_EXTRACTING_GAME: &str = "Extracting...";
 pub const PB_LABEL_DOWNLOADING_SPRITEPACKS: &str = "Downloading spritepacks...";
 pub const PB_LABEL_EXTRACTING_SPRITEPACKS: &str = "Extracting spritepacks...";
+pub const PB_LABEL_INSTALLING: &str = "Installing files...";
 pub const PB_LABEL_CLEANINGUP: &str = "Cleaning up...";
 
 
@@ -194,16 +241,20 @@ pub const LICENSE_FRAME_LABEL: &str = "By continuing you agree with our license"
 pub const SELECT_DIR_FRAME_LABEL: &str = "Select Doki Doki Literature Club directory";
 pub const OPTIONS_FRAME_LABEL: &str = "Select additional settings";
 pub const PROGRESS_FRAME_LABEL: &str = "Installing. Please wait";
+pub const PREVIEW_FRAME_LABEL: &str = "What the install would change";
+pub const PREVIEW_LOADING_MSG: &str = "Looking at the release, nothing will be changed...";
+pub const PREVIEW_FAILED_MSG: &str = "Couldn't preview the install:";
 pub const ABORT_TOP_FRAME_LABEL: &str = "Aborted";
 pub const ABORT_MID_FRAME_LABEL: &str = concat!(
     "Installation has been aborted.\n",
-    "Any already extracted files will remain"
+    "Your game files have been left as they were"
 );
 pub const DONE_TOP_FRAME_LABEL: &str = "Finished";
 pub const DONE_MID_FRAME_LABEL: &str = concat!(
     "Just Natsuki has been successfully\n",
     "installed on your computer"
 );
+pub const BACKUP_FRAME_LABEL: &str = "Replaced files have been backed up to:";
 pub const CREDITS_FRAME_LABEL: &str = "Original installer by MAS (Monika After Story) Team | Authored by Booplicate";
 
 
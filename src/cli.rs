@@ -0,0 +1,815 @@
+/// The module that implements the headless command line interface
+
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    slice::Iter
+};
+
+use crate::{
+    errors::InstallError,
+    installer::{
+        self,
+        cache,
+        dry_run,
+        InstallOptions,
+        release::{LocalArchives, ReleaseSelector},
+        reporter::{InstallEvent, Reporter},
+        uninstall,
+        update::{self, UpdateStatus}
+    },
+    settings::{Settings, SymlinkPolicy},
+    utils
+};
+
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_CANCELLED: i32 = 3;
+pub const EXIT_NOT_INSTALLED: i32 = 4;
+pub const EXIT_UNINSTALL_INCOMPLETE: i32 = 5;
+
+const USAGE: &str = concat!(
+    "Usage: jn-installer [COMMAND] [OPTIONS]\n",
+    "\n",
+    "Runs the graphical installer when no command is given.\n",
+    "\n",
+    "Commands:\n",
+    "    install            Install Just Natsuki without the GUI\n",
+    "    update             Update an existing install if there's a newer release\n",
+    "    uninstall          Remove the installed files and restore the backed up ones\n",
+    "    releases           List the releases available for installing\n",
+    "    clear-cache        Delete the cached releases and the partial and finished downloads\n",
+    "    help               Print this message\n",
+    "    version            Print the installer version\n",
+    "\n",
+    "Install options:\n",
+    "    -d, --dir <PATH>   DDLC directory to install into (default: current directory)\n",
+    "    -y, --yes          Don't ask for confirmation\n",
+    "    --spritepacks      Also install the spritepacks\n",
+    "    --version <TAG>    Install the release with the given tag\n",
+    "    --channel <NAME>   Install the newest release from 'stable' or 'beta' (default: stable)\n",
+    "    --from-archive <PATH>\n",
+    "                       Install from a downloaded jn-X.Y.Z.zip (or spritepacks) archive\n",
+    "                       instead of downloading, can be given twice\n",
+    "    --dry-run          Only print what the install would change, nothing is changed\n",
+    "    --allow-unsigned   Install the release even if it isn't signed (invalid signatures are still refused)\n",
+    "\n",
+    "Update options (same as install, except --from-archive):\n",
+    "    --force            Reinstall even if the installed version is up to date\n",
+    "\n",
+    "Uninstall options:\n",
+    "    -d, --dir <PATH>   DDLC directory to uninstall from (default: current directory)\n",
+    "    -y, --yes          Don't ask for confirmation\n",
+    "    --remove-spritepacks\n",
+    "                       Also delete the spritepacks folder\n",
+    "\n",
+    "Settings options (override the settings file and environment):\n",
+    "    --settings <PATH>  Settings file to use\n",
+    "    --api-url <URL>    Base URL of a GitHub compatible API\n",
+    "    --org <NAME>       Owner of the repository with the releases\n",
+    "    --repo <NAME>      Repository with the releases\n",
+    "    --insecure         Allow plain http URLs (for local stand-ins)\n",
+    "    --public-key <KEY> Minisign public key the releases are signed with (replaces the built-in one,\n",
+    "                       only use it for your own repository)\n",
+    "    --download-workers <N>\n",
+    "                       How many ranges of an asset to download at once (default: 4)\n",
+    "    --mirror <URL>     Mirror to try when GitHub fails, can be given several times\n",
+    "                       (\"{tag}\" and \"{name}\" are replaced with the tag and the asset name)\n",
+    "    --proxy <URL>      Proxy for all requests (http://, https://, socks5:// or socks5h://)\n",
+    "    --no-proxy <HOSTS> Comma-separated hosts that bypass the proxy\n",
+    "    --connect-timeout <SECS>\n",
+    "                       How long to wait for a connection (default: 10)\n",
+    "    --read-timeout <SECS>\n",
+    "                       How long to wait for data before a request fails (default: 30)\n",
+    "    --ca-bundle <PATH> PEM file with extra CA certificates to trust\n",
+    "    --github-token <TOKEN>\n",
+    "                       Token for the GitHub API, raises the rate limit\n",
+    "    --use-cache        Keep downloaded releases, so reinstalls don't download them again\n",
+    "    --cache-size-limit <MIB>\n",
+    "                       How much space the cached releases can take (default: 2048)\n",
+    "    --no-preserve-timestamps\n",
+    "                       Give the installed files the install time instead of the release one\n",
+    "    --max-archive-entries <N>\n",
+    "                       How many entries an archive can have (default: 100000)\n",
+    "    --max-extracted-size <MIB>\n",
+    "                       How much space the files of an archive can take (default: 16384)\n",
+    "    --max-compression-ratio <N>\n",
+    "                       How many times a file can be bigger than its compressed data (default: 100)\n",
+    "    --symlinks <POLICY>\n",
+    "                       What to do with symlinks in the way: refuse (default), follow or replace\n",
+    "\n",
+    "Exit codes:\n",
+    "    0 success, 2 bad usage, 3 cancelled, 4 not installed (update, uninstall),\n",
+    "    5 some files couldn't be uninstalled,\n",
+    "    10 download failed, 11 corrupted release data, 12 invalid JSON,\n",
+    "    13 request failed, 14 I/O failure, 15 extraction failed,\n",
+    "    16 invalid settings, 17 release not found, 18 bad archive name,\n",
+    "    19 missing archive, 20 release signature can't be verified,\n",
+    "    21 GitHub API rate limit exceeded, 22 no install manifest,\n",
+    "    23 not enough disk space or no write access\n",
+    "\n",
+    "On Windows the installer is a GUI app, so cmd doesn't wait for it to exit:\n",
+    "run it with 'start /wait' to get its exit code, and pass --yes, since\n",
+    "the confirmation prompt shares the console with cmd"
+);
+
+
+/// Settings overrides given on the command line
+#[derive(Default)]
+struct SettingsArgs {
+    settings_path: Option<PathBuf>,
+    api_base_url: Option<String>,
+    org_name: Option<String>,
+    repo_name: Option<String>,
+    allow_insecure: bool,
+    public_key: Option<String>,
+    download_workers: Option<usize>,
+    mirrors: Vec<String>,
+    proxy: Option<String>,
+    no_proxy: Option<String>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+    ca_bundle: Option<PathBuf>,
+    github_token: Option<String>,
+    use_cache: bool,
+    cache_size_limit: Option<u64>,
+    no_preserve_timestamps: bool,
+    max_archive_entries: Option<usize>,
+    max_extracted_size: Option<u64>,
+    max_compression_ratio: Option<u64>,
+    symlink_policy: Option<SymlinkPolicy>
+}
+
+impl SettingsArgs {
+    /// Tries to parse a settings option, returns false if the option isn't one of ours
+    fn parse_option(&mut self, option: &str, args: &mut Iter<String>) -> Result<bool, String> {
+        match option {
+            "--settings" => {
+                self.settings_path = Some(PathBuf::from(take_value(args, option)?));
+            },
+            "--api-url" => {
+                self.api_base_url = Some(take_value(args, option)?.clone());
+            },
+            "--org" => {
+                self.org_name = Some(take_value(args, option)?.clone());
+            },
+            "--repo" => {
+                self.repo_name = Some(take_value(args, option)?.clone());
+            },
+            "--insecure" => {
+                self.allow_insecure = true;
+            },
+            "--public-key" => {
+                self.public_key = Some(take_value(args, option)?.clone());
+            },
+            "--download-workers" => {
+                let value = take_value(args, option)?;
+                let workers = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid number of workers"))?;
+                self.download_workers = Some(workers);
+            },
+            "--mirror" => {
+                self.mirrors.push(take_value(args, option)?.clone());
+            },
+            "--proxy" => {
+                self.proxy = Some(take_value(args, option)?.clone());
+            },
+            "--no-proxy" => {
+                self.no_proxy = Some(take_value(args, option)?.clone());
+            },
+            "--connect-timeout" | "--read-timeout" => {
+                let value = take_value(args, option)?;
+                let timeout = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid number of seconds"))?;
+                if option == "--connect-timeout" {
+                    self.connect_timeout = Some(timeout);
+                }
+                else {
+                    self.read_timeout = Some(timeout);
+                }
+            },
+            "--ca-bundle" => {
+                self.ca_bundle = Some(PathBuf::from(take_value(args, option)?));
+            },
+            "--github-token" => {
+                self.github_token = Some(take_value(args, option)?.clone());
+            },
+            "--use-cache" => {
+                self.use_cache = true;
+            },
+            "--cache-size-limit" => {
+                let value = take_value(args, option)?;
+                let limit = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid size"))?;
+                self.cache_size_limit = Some(limit);
+            },
+            "--no-preserve-timestamps" => {
+                self.no_preserve_timestamps = true;
+            },
+            "--max-archive-entries" => {
+                let value = take_value(args, option)?;
+                let limit = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid number of entries"))?;
+                self.max_archive_entries = Some(limit);
+            },
+            "--max-extracted-size" => {
+                let value = take_value(args, option)?;
+                let limit = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid size"))?;
+                self.max_extracted_size = Some(limit);
+            },
+            "--max-compression-ratio" => {
+                let value = take_value(args, option)?;
+                let limit = value.parse()
+                    .map_err(|_| format!("'{value}' isn't a valid ratio"))?;
+                self.max_compression_ratio = Some(limit);
+            },
+            "--symlinks" => {
+                let value = take_value(args, option)?;
+                let policy = SymlinkPolicy::from_name(value)
+                    .ok_or_else(|| format!("unknown symlink policy '{value}'"))?;
+                self.symlink_policy = Some(policy);
+            },
+            _ => return Ok(false)
+        };
+        return Ok(true);
+    }
+
+    /// Loads the settings and applies the overrides
+    fn load(&self) -> Result<Settings, String> {
+        let mut settings = match self.settings_path {
+            Some(ref path) => {
+                Settings::load_from(path)
+                    .map_err(|e| format!("failed to load settings from '{}': {e}", path.display()))?
+            },
+            None => Settings::load()
+        };
+
+        if let Some(ref value) = self.api_base_url {
+            settings.api_base_url = value.clone();
+        }
+        if let Some(ref value) = self.org_name {
+            settings.org_name = value.clone();
+        }
+        if let Some(ref value) = self.repo_name {
+            settings.repo_name = value.clone();
+        }
+        if self.allow_insecure {
+            settings.allow_insecure = true;
+        }
+        if let Some(ref value) = self.public_key {
+            settings.public_key = value.clone();
+            if settings.has_custom_public_key() {
+                eprintln!("Warning: using the public key from --public-key instead of the built-in one, releases signed with it are trusted");
+            }
+        }
+        if let Some(value) = self.download_workers {
+            settings.download_workers = value;
+        }
+        if !self.mirrors.is_empty() {
+            settings.mirrors = self.mirrors.clone();
+        }
+        if let Some(ref value) = self.proxy {
+            settings.proxy = Some(value.clone());
+        }
+        if let Some(ref value) = self.no_proxy {
+            settings.no_proxy = Some(value.clone());
+        }
+        if let Some(value) = self.connect_timeout {
+            settings.connect_timeout = value;
+        }
+        if let Some(value) = self.read_timeout {
+            settings.read_timeout = value;
+        }
+        if let Some(ref value) = self.ca_bundle {
+            settings.ca_bundle = Some(value.clone());
+        }
+        if let Some(ref value) = self.github_token {
+            settings.github_token = Some(value.clone());
+        }
+        if self.use_cache {
+            settings.use_cache = true;
+        }
+        if let Some(value) = self.cache_size_limit {
+            settings.cache_size_limit = value;
+        }
+        if self.no_preserve_timestamps {
+            settings.preserve_timestamps = false;
+        }
+        if let Some(value) = self.max_archive_entries {
+            settings.max_archive_entries = value;
+        }
+        if let Some(value) = self.max_extracted_size {
+            settings.max_extracted_size = value;
+        }
+        if let Some(value) = self.max_compression_ratio {
+            settings.max_compression_ratio = value;
+        }
+        if let Some(value) = self.symlink_policy {
+            settings.symlink_policy = value;
+        }
+
+        return Ok(settings);
+    }
+}
+
+/// Options for the install command
+struct InstallArgs {
+    dir: PathBuf,
+    assume_yes: bool,
+    install_spr: bool,
+    release: ReleaseSelector,
+    archives: Vec<PathBuf>,
+    dry_run: bool,
+    allow_unsigned: bool,
+    // Only for the update command
+    force: bool,
+    settings: SettingsArgs
+}
+
+impl Default for InstallArgs {
+    fn default() -> Self {
+        return Self {
+            dir: utils::get_cwd(),
+            assume_yes: false,
+            install_spr: false,
+            release: ReleaseSelector::default(),
+            archives: Vec::new(),
+            dry_run: false,
+            allow_unsigned: false,
+            force: false,
+            settings: SettingsArgs::default()
+        };
+    }
+}
+
+/// Options for the uninstall command
+struct UninstallArgs {
+    dir: PathBuf,
+    assume_yes: bool,
+    remove_spr: bool
+}
+
+/// Commands the CLI can run
+enum Command {
+    Help,
+    Version,
+    Install(InstallArgs),
+    Update(InstallArgs),
+    Uninstall(UninstallArgs),
+    ListReleases(SettingsArgs),
+    ClearCache
+}
+
+
+/// Reporter that prints installer events as plain text
+struct ProgressPrinter {
+    // Last printed percent, negative if we haven't printed one for the current stage
+    last_percent: Cell<i32>
+}
+
+impl ProgressPrinter {
+    /// Creates a new ProgressPrinter
+    pub fn new() -> Self {
+        return Self { last_percent: Cell::new(-1) };
+    }
+
+    /// Prints a new stage on its own line
+    fn print_stage(&self, label: &str) {
+        // Finish the progress line of the previous stage
+        if self.last_percent.get() >= 0 {
+            println!();
+        }
+        self.last_percent.set(-1);
+        println!("{label}");
+    }
+}
+
+impl Reporter for ProgressPrinter {
+    fn report(&self, event: InstallEvent) {
+        match event {
+            InstallEvent::Progress(val) => {
+                let percent = (val * 100.0) as i32;
+                if percent != self.last_percent.get() {
+                    self.last_percent.set(percent);
+                    print!("\r    {percent:>3}%");
+                    // Not a big deal if this fails
+                    io::stdout().flush().ok();
+                }
+            },
+            InstallEvent::Preparing => self.print_stage("Preparing..."),
+            InstallEvent::Downloading => self.print_stage("Downloading..."),
+            InstallEvent::Extracting => self.print_stage("Extracting..."),
+            InstallEvent::DownloadingSpr => self.print_stage("Downloading spritepacks..."),
+            InstallEvent::ExtractingSpr => self.print_stage("Extracting spritepacks..."),
+            InstallEvent::Installing => self.print_stage("Installing files..."),
+            InstallEvent::CleaningUp => self.print_stage("Cleaning up..."),
+            InstallEvent::Done => self.print_stage("Installation is complete!"),
+            InstallEvent::Uninstalling => self.print_stage("Uninstalling...")
+        };
+    }
+
+    fn report_backup(&self, path: &Path) {
+        self.print_stage(&format!("Replaced files have been backed up to '{}'", path.display()));
+    }
+
+    fn is_aborted(&self) -> bool {
+        return utils::is_interrupted();
+    }
+}
+
+
+/// Checks if the given arguments ask for the CLI instead of the GUI
+pub fn is_cli_invocation(args: &[String]) -> bool {
+    return match args.first() {
+        // Old macOS versions pass the process serial number to apps launched from Finder
+        Some(arg) => !arg.starts_with("-psn_"),
+        None => false
+    };
+}
+
+/// Returns the value following the given option
+fn take_value<'a>(args: &mut Iter<'a, String>, option: &str) -> Result<&'a String, String> {
+    return args.next().ok_or_else(|| format!("'{option}' requires a value"));
+}
+
+/// Parses command line arguments (without the program name)
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut args = args.iter();
+
+    let cmd = match args.next() {
+        Some(cmd) => cmd.as_str(),
+        None => return Ok(Command::Help)
+    };
+    match cmd {
+        "help" | "-h" | "--help" => return Ok(Command::Help),
+        "version" | "-V" | "--version" => return Ok(Command::Version),
+        "install" | "update" => {},
+        "releases" => {
+            let mut settings_args = SettingsArgs::default();
+            while let Some(arg) = args.next() {
+                if !settings_args.parse_option(arg, &mut args)? {
+                    return Err(format!("unknown option '{arg}'"));
+                }
+            }
+            return Ok(Command::ListReleases(settings_args));
+        },
+        "uninstall" => {
+            let mut uninstall_args = UninstallArgs { dir: utils::get_cwd(), assume_yes: false, remove_spr: false };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "-d" | "--dir" => {
+                        uninstall_args.dir = PathBuf::from(take_value(&mut args, arg)?);
+                    },
+                    "-y" | "--yes" => {
+                        uninstall_args.assume_yes = true;
+                    },
+                    "--remove-spritepacks" => {
+                        uninstall_args.remove_spr = true;
+                    },
+                    _ => return Err(format!("unknown option '{arg}'"))
+                };
+            }
+            return Ok(Command::Uninstall(uninstall_args));
+        },
+        "clear-cache" => {
+            if let Some(arg) = args.next() {
+                return Err(format!("unknown option '{arg}'"));
+            }
+            return Ok(Command::ClearCache);
+        },
+        _ => return Err(format!("unknown command '{cmd}'"))
+    };
+
+    let is_update = cmd == "update";
+    let mut install_args = InstallArgs::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-d" | "--dir" => {
+                install_args.dir = PathBuf::from(take_value(&mut args, arg)?);
+            },
+            "-y" | "--yes" => {
+                install_args.assume_yes = true;
+            },
+            "--spritepacks" => {
+                install_args.install_spr = true;
+            },
+            "--version" => {
+                install_args.release = ReleaseSelector::Tag(take_value(&mut args, arg)?.clone());
+            },
+            "--from-archive" if !is_update => {
+                install_args.archives.push(PathBuf::from(take_value(&mut args, arg)?));
+            },
+            "--channel" => {
+                let channel = take_value(&mut args, arg)?;
+                install_args.release = ReleaseSelector::from_channel(channel)
+                    .ok_or_else(|| format!("unknown channel '{channel}'"))?;
+            },
+            "--dry-run" => {
+                install_args.dry_run = true;
+            },
+            "--allow-unsigned" => {
+                install_args.allow_unsigned = true;
+            },
+            "--force" if is_update => {
+                install_args.force = true;
+            },
+            _ => {
+                if !install_args.settings.parse_option(arg, &mut args)? {
+                    return Err(format!("unknown option '{arg}'"));
+                }
+            }
+        };
+    }
+
+    return match is_update {
+        true => Ok(Command::Update(install_args)),
+        false => Ok(Command::Install(install_args))
+    };
+}
+
+/// Asks the user a yes/no question on stdin, defaults to no
+fn confirm(prompt: &str) -> bool {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush().ok();
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    return matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+}
+
+/// Builds the install options from the arguments
+/// Prints what's wrong and returns the exit code if they're invalid
+fn build_install_options(args: &InstallArgs) -> Result<InstallOptions, i32> {
+    let settings = match args.settings.load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return Err(EXIT_USAGE);
+        }
+    };
+    if settings.allow_insecure {
+        eprintln!("Warning: insecure mode is enabled, plain http URLs are allowed");
+    }
+    if !args.dir.is_dir() {
+        eprintln!("Error: '{}' is not a directory", args.dir.display());
+        return Err(EXIT_USAGE);
+    }
+    if !utils::is_valid_ddlc_dir(&args.dir) {
+        eprintln!("Warning: '{}' doesn't appear to be a valid DDLC directory", args.dir.display());
+    }
+
+    let mut options = InstallOptions::new(args.dir.clone(), args.install_spr, args.release.clone(), settings);
+    options.allow_unsigned = args.allow_unsigned;
+    if !args.archives.is_empty() {
+        match LocalArchives::from_paths(&args.archives) {
+            Ok(archives) => {
+                // Giving us the spritepacks means you want them
+                options.install_spr |= archives.spritepacks.is_some();
+                options.local_archives = Some(archives);
+            },
+            Err(e) => {
+                eprintln!("Error: {e}");
+                return Err(e.exit_code());
+            }
+        };
+    }
+
+    return Ok(options);
+}
+
+/// Tells how to install an unsigned release anyway, if that's why the install has failed
+fn print_unsigned_hint(error: &InstallError) {
+    if let InstallError::SignatureError(e) = error {
+        if e.is_unsigned() {
+            eprintln!("Use --allow-unsigned if you trust this release anyway");
+        }
+    }
+}
+
+/// Prints what the install would change
+fn print_install_plan(options: &InstallOptions) -> i32 {
+    return match dry_run::plan_install(options) {
+        Ok(plan) => {
+            println!("{plan}");
+            EXIT_SUCCESS
+        },
+        Err(e) => {
+            eprintln!("Dry run failed: {e}");
+            print_unsigned_hint(&e);
+            e.exit_code()
+        }
+    };
+}
+
+/// Asks for confirmation unless we've been told not to, then installs
+/// Only prints what would be changed for dry runs
+fn confirm_and_install(options: &InstallOptions, prompt: &str, args: &InstallArgs) -> i32 {
+    if args.dry_run {
+        return print_install_plan(options);
+    }
+    if !args.assume_yes && !confirm(prompt) {
+        println!("Installation has been cancelled");
+        return EXIT_CANCELLED;
+    }
+
+    let printer = ProgressPrinter::new();
+    // Ctrl+C aborts the install, instead of leaving the game dir half-installed
+    utils::set_interrupt_handler();
+
+    return match installer::install_game(options, &printer) {
+        Ok(_) if utils::is_interrupted() => {
+            println!("\nInstallation has been aborted");
+            EXIT_CANCELLED
+        },
+        Ok(_) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("\nInstallation failed: {e}");
+            print_unsigned_hint(&e);
+            e.exit_code()
+        }
+    };
+}
+
+/// Runs the install command
+fn run_install(args: InstallArgs) -> i32 {
+    let options = match build_install_options(&args) {
+        Ok(options) => options,
+        Err(exit_code) => return exit_code
+    };
+
+    let prompt = format!("Install Just Natsuki into '{}'?", options.extraction_dir.display());
+
+    return confirm_and_install(&options, &prompt, &args);
+}
+
+/// Runs the update command
+fn run_update(args: InstallArgs) -> i32 {
+    let mut options = match build_install_options(&args) {
+        Ok(options) => options,
+        Err(exit_code) => return exit_code
+    };
+
+    let install = match update::detect_install(&options.extraction_dir) {
+        Some(install) => install,
+        None => {
+            eprintln!(
+                "Error: Just Natsuki isn't installed in '{}', use the install command",
+                options.extraction_dir.display()
+            );
+            return EXIT_NOT_INSTALLED;
+        }
+    };
+    let release_version = match update::get_release_version(&options) {
+        Ok(version) => version,
+        Err(e) => {
+            eprintln!("Failed to check for updates: {e}");
+            return e.exit_code();
+        }
+    };
+    let installed_version = install.version.as_deref().unwrap_or("(unknown version)");
+    println!("Installed: {installed_version}, available: {release_version}");
+
+    let status = update::get_update_status(Some(&install), &release_version);
+    let prompt = match status {
+        UpdateStatus::UpToDate | UpdateStatus::NewerInstalled if !args.force => {
+            if status == UpdateStatus::UpToDate {
+                println!("Just Natsuki is up to date");
+            }
+            else {
+                println!("The installed version is newer than the release");
+            }
+            println!("Use --force to reinstall {release_version}");
+            return EXIT_SUCCESS;
+        },
+        UpdateStatus::UpToDate | UpdateStatus::NewerInstalled => {
+            format!("Reinstall Just Natsuki {release_version} into '{}'?", options.extraction_dir.display())
+        },
+        _ => {
+            format!(
+                "Update Just Natsuki {installed_version} to {release_version} in '{}'?",
+                options.extraction_dir.display()
+            )
+        }
+    };
+
+    // Install exactly what we've checked, even if a new release comes out meanwhile
+    options.release = ReleaseSelector::Tag(release_version);
+    // Keep the spritepacks up to date too
+    options.install_spr |= install.has_spritepacks;
+
+    return confirm_and_install(&options, &prompt, &args);
+}
+
+/// Runs the uninstall command
+fn run_uninstall(args: UninstallArgs) -> i32 {
+    let install = match update::detect_install(&args.dir) {
+        Some(install) => install,
+        None => {
+            eprintln!("Error: Just Natsuki isn't installed in '{}'", args.dir.display());
+            return EXIT_NOT_INSTALLED;
+        }
+    };
+    let version = install.version.as_deref().unwrap_or("(unknown version)");
+
+    let prompt = match args.remove_spr {
+        true => format!("Uninstall Just Natsuki {version} and its spritepacks from '{}'?", args.dir.display()),
+        false => format!("Uninstall Just Natsuki {version} from '{}'?", args.dir.display())
+    };
+    if !args.assume_yes && !confirm(&prompt) {
+        println!("Uninstallation has been cancelled");
+        return EXIT_CANCELLED;
+    }
+
+    let printer = ProgressPrinter::new();
+    let report = match uninstall::uninstall(&printer, &args.dir, args.remove_spr) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("\nUninstallation failed: {e}");
+            return e.exit_code();
+        }
+    };
+    printer.print_stage(&format!("Removed {} files, restored {} files", report.removed_files, report.restored_files));
+    if report.failures.is_empty() {
+        println!("Just Natsuki has been uninstalled");
+        return EXIT_SUCCESS;
+    }
+
+    eprintln!("Couldn't uninstall these files:");
+    for failure in report.failures {
+        eprintln!("    {failure}");
+    }
+    return EXIT_UNINSTALL_INCOMPLETE;
+}
+
+/// Runs the releases command
+fn run_list_releases(args: SettingsArgs) -> i32 {
+    let settings = match args.load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return EXIT_USAGE;
+        }
+    };
+
+    return match installer::get_available_releases(&settings) {
+        Ok(releases) => {
+            for data in releases {
+                let suffix = if data.is_prerelease { " (pre-release)" } else { "" };
+                println!("{}    {}{}", data.version, data.name, suffix);
+            }
+            EXIT_SUCCESS
+        },
+        Err(e) => {
+            eprintln!("Failed to list releases: {e}");
+            e.exit_code()
+        }
+    };
+}
+
+/// Runs the clear-cache command
+fn run_clear_cache() -> i32 {
+    return match cache::clear_cache() {
+        Ok(freed) => {
+            println!("Freed {:.1} MiB", freed as f64 / (1024.0*1024.0));
+            EXIT_SUCCESS
+        },
+        Err(e) => {
+            let e = InstallError::from(e);
+            eprintln!("Failed to clear the cache: {e}");
+            e.exit_code()
+        }
+    };
+}
+
+/// Runs the CLI with the given arguments (without the program name)
+/// Returns the exit code for the process
+pub fn run(args: &[String]) -> i32 {
+    let cmd = match parse_args(args) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            eprintln!("Error: {e}\n\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+
+    return match cmd {
+        Command::Help => {
+            println!("{USAGE}");
+            EXIT_SUCCESS
+        },
+        Command::Version => {
+            println!("{}", crate::VERSION.unwrap_or(crate::DEF_VERSION));
+            EXIT_SUCCESS
+        },
+        Command::Install(install_args) => run_install(install_args),
+        Command::Update(install_args) => run_update(install_args),
+        Command::Uninstall(uninstall_args) => run_uninstall(uninstall_args),
+        Command::ListReleases(settings_args) => run_list_releases(settings_args),
+        Command::ClearCache => run_clear_cache()
+    };
+}
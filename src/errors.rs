@@ -2,6 +2,8 @@
 use std::io::Error as IOError;
 use std::error::Error as StdError;
 use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 use zip::result::ZipError;
 use reqwest::{
@@ -9,6 +11,7 @@ use reqwest::{
     StatusCode
 };
 use serde_json::Error as SerdeError;
+use minisign_verify::Error as MinisignError;
 use rodio::{
     PlayError,
     StreamError,
@@ -28,7 +31,15 @@ pub enum DownloadError {
     /// while downloading the assets
     InvalidStatusCode(StatusCode),
     /// General IO failure, couldn't write/read
-    IOError(IOError)
+    IOError(IOError),
+    /// Downloaded a different amount of data than expected (expected, received)
+    SizeMismatch(u64, u64),
+    /// The SHA-256 digest of the downloaded data is wrong (asset name)
+    ChecksumMismatch(String),
+    /// The release checksums don't include the asset (asset name)
+    MissingChecksum(String),
+    /// The server sends the whole asset when we ask for a part of it
+    RangeNotSupported
 }
 
 impl From<ReqError> for DownloadError {
@@ -66,6 +77,18 @@ impl fmt::Display for DownloadError {
             },
             Self::IOError(err) => {
                 write!(f, "failed to read/write data: {}", err)
+            },
+            Self::SizeMismatch(expected, received) => {
+                write!(f, "expected {} bytes, but received {}", expected, received)
+            },
+            Self::ChecksumMismatch(name) => {
+                write!(f, "checksum of '{}' doesn't match, the file may be corrupted or tampered with", name)
+            },
+            Self::MissingChecksum(name) => {
+                write!(f, "the release doesn't provide a checksum for '{}'", name)
+            },
+            Self::RangeNotSupported => {
+                write!(f, "the server doesn't support partial downloads")
             }
         };
     }
@@ -80,7 +103,21 @@ pub enum ExtractionError {
     /// Unsafe file path in the archive, possible attack?
     UnsafeFilepath(String),
     /// I/O error
-    IOError(IOError)
+    IOError(IOError),
+    /// The archive has more entries than the limit (entries, limit)
+    TooManyEntries(usize, usize),
+    /// The extracted files would take more bytes than the limit (size, limit)
+    TooLarge(u64, u64),
+    /// An entry is compressed way better than real files are, likely a zip bomb
+    SuspiciousCompression(String),
+    /// An entry has more data than the archive says
+    SizeMismatch(String),
+    /// A dir on the way to a file we install is a symlink, and the policy doesn't allow it
+    SymlinkInDestination(String),
+    /// The archive has a symlink, and the policy doesn't allow it
+    SymlinkEntry(String),
+    /// A file would end up outside of the dir it's installed into
+    OutsideDestination(String)
 }
 
 impl From<ZipError> for ExtractionError {
@@ -115,6 +152,176 @@ impl fmt::Display for ExtractionError {
             },
             Self::IOError(err) => {
                 write!(f, "failed to read/write data: {}", err)
+            },
+            Self::TooManyEntries(entries, limit) => {
+                write!(f, "the archive has {} entries, the limit is {}", entries, limit)
+            },
+            Self::TooLarge(size, limit) => {
+                write!(
+                    f,
+                    "the archive would take {} MiB extracted, the limit is {} MiB",
+                    size / (1024*1024),
+                    limit / (1024*1024)
+                )
+            },
+            Self::SuspiciousCompression(name) => {
+                write!(f, "'{}' is compressed suspiciously well, the archive might be a zip bomb", name)
+            },
+            Self::SizeMismatch(name) => {
+                write!(f, "'{}' is bigger than the archive says", name)
+            },
+            Self::SymlinkInDestination(path) => {
+                write!(f, "'{}' is a symlink, change the symlink policy to install thru it", path)
+            },
+            Self::SymlinkEntry(name) => {
+                write!(f, "the archive has a symlink '{}', change the symlink policy to install it", name)
+            },
+            Self::OutsideDestination(path) => {
+                write!(f, "'{}' leads outside of the install dir", path)
+            }
+        };
+    }
+}
+
+
+/// Error type repesenting invalid installer settings
+#[derive(Debug)]
+pub enum SettingsError {
+    /// Couldn't read the settings file
+    IOError(IOError),
+    /// The settings file isn't valid JSON
+    InvalidJson(SerdeError),
+    /// A required value is empty
+    MissingValue(&'static str),
+    /// A value is out of its range
+    InvalidValue(&'static str),
+    /// A URL doesn't use https while insecure mode is off
+    InsecureUrl(String)
+}
+
+impl From<IOError> for SettingsError {
+    fn from(err: IOError) -> Self {
+        return Self::IOError(err);
+    }
+}
+impl From<SerdeError> for SettingsError {
+    fn from(err: SerdeError) -> Self {
+        return Self::InvalidJson(err);
+    }
+}
+
+impl StdError for SettingsError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        return match self {
+            Self::IOError(og_err) => Some(og_err),
+            Self::InvalidJson(og_err) => Some(og_err),
+            _ => None
+        };
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::IOError(err) => {
+                write!(f, "failed to read settings: {}", err)
+            },
+            Self::InvalidJson(err) => {
+                write!(f, "invalid settings file: {}", err)
+            },
+            Self::MissingValue(name) => {
+                write!(f, "'{}' must not be empty", name)
+            },
+            Self::InvalidValue(name) => {
+                write!(f, "'{}' has an invalid value", name)
+            },
+            Self::InsecureUrl(url) => {
+                write!(f, "'{}' doesn't use https, enable insecure mode to allow it", url)
+            }
+        };
+    }
+}
+
+
+/// Error type repesenting a release we can't trust
+#[derive(Debug)]
+pub enum SignatureError {
+    /// The release doesn't provide checksums
+    MissingChecksums,
+    /// The release doesn't provide a signature for the checksums
+    MissingSignature,
+    /// The public key from the settings is broken
+    InvalidPublicKey(MinisignError),
+    /// The signature is broken or wasn't made with our key
+    InvalidSignature(MinisignError)
+}
+
+impl SignatureError {
+    /// Checks if the release just isn't signed, rather than signed with the wrong key or tampered with
+    pub fn is_unsigned(&self) -> bool {
+        return matches!(self, Self::MissingChecksums | Self::MissingSignature);
+    }
+}
+
+impl StdError for SignatureError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        return match self {
+            Self::InvalidPublicKey(og_err) => Some(og_err),
+            Self::InvalidSignature(og_err) => Some(og_err),
+            _ => None
+        };
+    }
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::MissingChecksums => {
+                write!(f, "the release doesn't provide checksums")
+            },
+            Self::MissingSignature => {
+                write!(f, "the release isn't signed")
+            },
+            Self::InvalidPublicKey(err) => {
+                write!(f, "invalid public key: {}", err)
+            },
+            Self::InvalidSignature(err) => {
+                write!(f, "invalid signature: {}", err)
+            }
+        };
+    }
+}
+
+
+/// Something that keeps an install from going ahead, found before anything is changed
+#[derive(Debug)]
+pub enum PreflightProblem {
+    /// There's not enough free space where the path is
+    NotEnoughSpace {
+        path: PathBuf,
+        needed: u64,
+        available: u64
+    },
+    /// We can't create files in the dir
+    NotWritable(PathBuf, IOError)
+}
+
+impl fmt::Display for PreflightProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: f64 = 1024.0*1024.0;
+
+        return match self {
+            Self::NotEnoughSpace { path, needed, available } => {
+                write!(
+                    f,
+                    "not enough free space for '{}': {:.1} MiB needed, {:.1} MiB free",
+                    path.display(),
+                    *needed as f64 / MIB,
+                    *available as f64 / MIB
+                )
+            },
+            Self::NotWritable(path, err) => {
+                write!(f, "can't write into '{}': {}", path.display(), err)
             }
         };
     }
@@ -136,7 +343,45 @@ pub enum InstallError {
     /// General IO failure, couldn't write/read
     IOError(IOError),
     /// Error occured during extraction
-    ExtractionError(ExtractionError)
+    ExtractionError(ExtractionError),
+    /// The installer settings are invalid
+    SettingsError(SettingsError),
+    /// The requested release doesn't exist
+    ReleaseNotFound(String),
+    /// A local archive doesn't look like a release asset
+    InvalidArchiveName(String),
+    /// A required local archive wasn't provided
+    MissingArchive(&'static str),
+    /// The release signature couldn't be verified
+    SignatureError(SignatureError),
+    /// GitHub API rate limit is exhausted (when it resets, if known)
+    RateLimited(Option<SystemTime>),
+    /// There's no install manifest, so we don't know what's been installed
+    MissingManifest,
+    /// The install can't go ahead, nothing has been changed
+    PreflightFailed(Vec<PreflightProblem>)
+}
+
+impl InstallError {
+    /// Returns the process exit code used by the CLI for this error
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            Self::DownloadError(_) => 10,
+            Self::CorruptedJSON(_) => 11,
+            Self::InvalidJson(_) => 12,
+            Self::RequestError(_) => 13,
+            Self::IOError(_) => 14,
+            Self::ExtractionError(_) => 15,
+            Self::SettingsError(_) => 16,
+            Self::ReleaseNotFound(_) => 17,
+            Self::InvalidArchiveName(_) => 18,
+            Self::MissingArchive(_) => 19,
+            Self::SignatureError(_) => 20,
+            Self::RateLimited(_) => 21,
+            Self::MissingManifest => 22,
+            Self::PreflightFailed(_) => 23
+        };
+    }
 }
 
 impl From<SerdeError> for InstallError {
@@ -164,6 +409,16 @@ impl From<ExtractionError> for InstallError{
         return Self::ExtractionError(err);
     }
 }
+impl From<SettingsError> for InstallError {
+    fn from(err: SettingsError) -> Self {
+        return Self::SettingsError(err);
+    }
+}
+impl From<SignatureError> for InstallError {
+    fn from(err: SignatureError) -> Self {
+        return Self::SignatureError(err);
+    }
+}
 
 impl StdError for InstallError {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
@@ -173,6 +428,8 @@ impl StdError for InstallError {
             Self::RequestError(og_err) => Some(og_err),
             Self::IOError(og_err) => Some(og_err),
             Self::ExtractionError(og_err) => Some(og_err),
+            Self::SettingsError(og_err) => Some(og_err),
+            Self::SignatureError(og_err) => Some(og_err),
             _ => None
         };
     }
@@ -198,6 +455,43 @@ impl fmt::Display for InstallError {
             },
             Self::ExtractionError(err) => {
                 write!(f, "extraction failed: {}", err)
+            },
+            Self::SettingsError(err) => {
+                write!(f, "invalid settings: {}", err)
+            },
+            Self::ReleaseNotFound(release) => {
+                write!(f, "couldn't find release '{}'", release)
+            },
+            Self::InvalidArchiveName(path) => {
+                write!(f, "'{}' doesn't look like a Just Natsuki release archive", path)
+            },
+            Self::MissingArchive(kind) => {
+                write!(f, "the {} archive is missing", kind)
+            },
+            Self::SignatureError(err) => {
+                write!(f, "couldn't verify the release: {}", err)
+            },
+            Self::RateLimited(Some(reset)) => {
+                let secs = reset.duration_since(SystemTime::now()).unwrap_or_default().as_secs();
+                write!(
+                    f,
+                    "GitHub API rate limit exceeded, try again in {} min ({}) or set a GitHub token",
+                    (secs + 59) / 60,
+                    httpdate::fmt_http_date(*reset)
+                )
+            },
+            Self::RateLimited(None) => {
+                write!(f, "GitHub API rate limit exceeded, try again later or set a GitHub token")
+            },
+            Self::MissingManifest => {
+                write!(f, "there's no install manifest, so the installed files are unknown")
+            },
+            Self::PreflightFailed(problems) => {
+                write!(f, "the install can't go ahead:")?;
+                for problem in problems {
+                    write!(f, "\n- {}", problem)?;
+                }
+                Ok(())
             }
         };
     }
@@ -0,0 +1,326 @@
+/// The module that implements backups of the files the installer has deleted or overwritten
+/// They're recorded in the install manifest, so uninstalling can put them back
+
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use serde::{Serialize, Deserialize};
+
+use super::{
+    get_component_dir,
+    get_game_dir,
+    join_relative_path,
+    manifest::{self, InstallManifest},
+    move_file,
+    remove_empty_dirs
+};
+
+
+/// Name of the dir the backups are kept in, it's in the game dir
+/// NOTE: not in game/, Ren'Py would load the scripts from there
+pub const BACKUPS_DIR_NAME: &str = "jn_backups";
+
+
+/// A file we've backed up
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackedUpFile {
+    /// The dir it's from, one of the COMPONENT_* names
+    pub component: String,
+    /// Path relative to the dir of the component, with '/' separators
+    pub path: String
+}
+
+/// A backup made by an install
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    /// Path of the backup dir relative to the game dir, with '/' separators
+    pub dir: String,
+    /// When it's been made, in seconds since the Unix epoch
+    pub created_at: u64,
+    pub files: Vec<BackedUpFile>,
+    /// The DDLC dir, only needed while we're making the backup
+    #[serde(skip)]
+    extraction_dir: PathBuf,
+    /// Components and paths of the files from the previous install, they can be downloaded again,
+    /// so there's no point in keeping them
+    #[serde(skip)]
+    installed_files: HashSet<(String, String)>,
+    /// The files of the previous install we've moved out of the way,
+    /// they're only kept until the install is finished, in case it's rolled back
+    #[serde(skip)]
+    discarded_files: Vec<BackedUpFile>
+}
+
+/// What restoring a backup has done
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub restored_files: usize,
+    /// The files we couldn't restore, with the reasons
+    pub failures: Vec<String>
+}
+
+impl Backup {
+    /// Creates an empty backup in a new timestamped dir (the dir is created once a file is added)
+    /// The files of the previous install aren't kept after the install is finished
+    pub fn new(extraction_dir: &Path, previous: Option<&InstallManifest>) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let installed_files = previous
+            .map(|manifest| {
+                manifest.components.iter()
+                    .flat_map(|component| {
+                        component.files.iter().map(move |file| (component.name.clone(), file.path.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Installs within the same second would share the dir otherwise
+        let backups_dir = get_game_dir(extraction_dir).join(BACKUPS_DIR_NAME);
+        let name = format_timestamp(created_at);
+        let mut dir_name = name.clone();
+        let mut i = 1;
+        while backups_dir.join(&dir_name).exists() {
+            i += 1;
+            dir_name = format!("{name}_{i}");
+        }
+
+        return Self {
+            dir: format!("{BACKUPS_DIR_NAME}/{dir_name}"),
+            created_at,
+            files: Vec::new(),
+            extraction_dir: extraction_dir.to_path_buf(),
+            installed_files,
+            discarded_files: Vec::new()
+        };
+    }
+
+    /// Reads the backup in the given dir (relative to the game dir, with '/' separators)
+    /// from the files in it, it's how we find out what an interrupted install has moved out of the way
+    pub fn read_dir(extraction_dir: &Path, dir: &str) -> io::Result<Self> {
+        let backup_dir = join_relative_path(&get_game_dir(extraction_dir), dir)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unsafe path '{dir}'")))?;
+
+        let mut files = Vec::new();
+        if backup_dir.is_dir() {
+            for entry in fs::read_dir(&backup_dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let component = entry.file_name();
+                let component = component.to_str()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the file name isn't valid UTF-8"))?;
+                find_backed_up_files(&entry.path(), component, "", &mut files)?;
+            }
+        }
+
+        return Ok(
+            Self {
+                dir: dir.to_string(),
+                created_at: 0,
+                files,
+                extraction_dir: extraction_dir.to_path_buf(),
+                installed_files: HashSet::new(),
+                discarded_files: Vec::new()
+            }
+        );
+    }
+
+    /// Checks if nothing worth keeping has been backed up
+    pub fn is_empty(&self) -> bool {
+        return self.files.is_empty();
+    }
+
+    /// Returns the path to the backup dir
+    pub fn get_path(&self) -> PathBuf {
+        // The dir is made by us, so it's always a relative path
+        return get_game_dir(&self.extraction_dir).join(&self.dir);
+    }
+
+    /// Moves a file out of the way into the backup
+    /// The path is relative to the dir of the component, with '/' separators
+    pub fn remove_file(&mut self, component: &str, path: &str) -> io::Result<()> {
+        let source = get_component_dir(&self.extraction_dir, component)
+            .and_then(|root| join_relative_path(&root, path))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unsafe path '{path}'")))?;
+
+        // Then it's a file this install has put there, the original is already in the backup
+        let is_backed_up = self.files.iter()
+            .chain(&self.discarded_files)
+            .any(|file| file.component == component && file.path == path);
+        if is_backed_up {
+            return fs::remove_file(&source);
+        }
+
+        let target = join_relative_path(&self.get_path().join(component), path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unsafe path '{path}'")))?;
+        move_file(&source, &target)?;
+
+        let file = BackedUpFile { component: component.to_string(), path: path.to_string() };
+        match self.installed_files.contains(&(file.component.clone(), file.path.clone())) {
+            true => self.discarded_files.push(file),
+            false => self.files.push(file)
+        };
+
+        return Ok(());
+    }
+
+    /// Moves a dir out of the way into the backup, file by file
+    pub fn remove_dir_all(&mut self, component: &str, path: &str) -> io::Result<()> {
+        let dir = get_component_dir(&self.extraction_dir, component)
+            .and_then(|root| join_relative_path(&root, path))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unsafe path '{path}'")))?;
+
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_str()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the file name isn't valid UTF-8"))?;
+            let entry_path = format!("{path}/{name}");
+            if entry.file_type()?.is_dir() {
+                self.remove_dir_all(component, &entry_path)?;
+            }
+            else {
+                self.remove_file(component, &entry_path)?;
+            }
+        }
+
+        return fs::remove_dir_all(&dir);
+    }
+
+    /// Moves the backed up files back where they were, files in the way are replaced
+    /// The backup dir is deleted if everything has been restored
+    /// If skip_spritepacks is true, the files of the spritepacks dir stay in the backup
+    /// NOTE: this is also how an unfinished install is rolled back
+    pub fn restore(&self, extraction_dir: &Path, skip_spritepacks: bool) -> RestoreReport {
+        let mut report = RestoreReport::default();
+        let backup_dir = match join_relative_path(&get_game_dir(extraction_dir), &self.dir) {
+            Some(path) => path,
+            None => {
+                report.failures.push(format!("'{}': unsafe backup path", self.dir));
+                return report;
+            }
+        };
+
+        let mut has_skipped = false;
+        for file in self.files.iter().chain(&self.discarded_files) {
+            if skip_spritepacks && file.component == manifest::COMPONENT_SPRITEPACKS {
+                has_skipped = true;
+                continue;
+            }
+            let paths = join_relative_path(&backup_dir.join(&file.component), &file.path)
+                .zip(
+                    get_component_dir(extraction_dir, &file.component)
+                        .and_then(|root| join_relative_path(&root, &file.path))
+                );
+            let (source, target) = match paths {
+                Some(paths) => paths,
+                None => {
+                    report.failures.push(format!("'{}/{}': unsafe path", file.component, file.path));
+                    continue;
+                }
+            };
+
+            match move_file(&source, &target) {
+                Ok(_) => report.restored_files += 1,
+                Err(e) => report.failures.push(format!("'{}': {e}", target.display()))
+            };
+        }
+
+        if report.failures.is_empty() && !has_skipped {
+            remove_backup_dir(&backup_dir, &get_game_dir(extraction_dir));
+        }
+
+        return report;
+    }
+
+    /// Deletes the files of the previous install from the backup once the install is finished,
+    /// the backup dir is deleted if nothing else has been backed up
+    pub fn finish(&self) {
+        let backup_dir = self.get_path();
+        if self.is_empty() {
+            remove_backup_dir(&backup_dir, &get_game_dir(&self.extraction_dir));
+            return;
+        }
+
+        for file in &self.discarded_files {
+            if let Some(path) = join_relative_path(&backup_dir.join(&file.component), &file.path) {
+                if let Err(e) = fs::remove_file(&path) {
+                    eprintln!("Failed to delete '{}': {e}", path.display());
+                }
+                remove_empty_dirs(path.parent(), &backup_dir);
+            }
+        }
+    }
+}
+
+
+/// Adds the files in the given dir of a component backup to files, path is the path of the dir
+/// relative to the dir of the component (empty for the dir itself)
+fn find_backed_up_files(dir: &Path, component: &str, path: &str, files: &mut Vec<BackedUpFile>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the file name isn't valid UTF-8"))?;
+        let entry_path = match path {
+            "" => name.to_string(),
+            _ => format!("{path}/{name}")
+        };
+        if entry.file_type()?.is_dir() {
+            find_backed_up_files(&entry.path(), component, &entry_path, files)?;
+        }
+        else {
+            files.push(BackedUpFile { component: component.to_string(), path: entry_path });
+        }
+    }
+
+    return Ok(());
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time that can be used in paths
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+
+    // Converts days to the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era*146097;
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2) / 153;
+    let day = doy - (153*mp + 2)/5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era*400 + if month <= 2 { 1 } else { 0 };
+
+    return format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+}
+
+/// Deletes a backup dir, and the dir with the backups if it's the last one
+fn remove_backup_dir(backup_dir: &Path, game_dir: &Path) {
+    if backup_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(backup_dir) {
+            eprintln!("Failed to delete the backup '{}': {e}", backup_dir.display());
+        }
+    }
+    // This fails if there are other backups, which is what we want
+    if let Some(parent_dir) = backup_dir.parent() {
+        if parent_dir != game_dir {
+            fs::remove_dir(parent_dir).ok();
+        }
+    }
+}
@@ -0,0 +1,223 @@
+/// The module that implements the cache of verified release assets
+/// Assets are kept by release source (the repository API URL), tag and name,
+/// so reinstalling or installing into another DDLC dir doesn't download them again
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime
+};
+
+use sha2::{Sha256, Digest};
+
+use crate::{
+    errors::DownloadError,
+    settings::Settings,
+    utils
+};
+use super::{
+    checksum::{self, Checksums},
+    download::{self, PartialDownload},
+    release::GHAsset,
+    ContentSize
+};
+
+
+const MIB: ContentSize = 1024*1024;
+
+
+/// The cached assets of a release
+pub struct ReleaseCache {
+    root: PathBuf,
+    dir: PathBuf,
+    size_limit: ContentSize
+}
+
+impl ReleaseCache {
+    /// Opens the cache for the given release, returns None if the settings don't allow caching
+    /// Releases without checksums are never cached, we couldn't tell a cached asset is still theirs
+    pub fn open(settings: &Settings, tag: &str, checksums: &Checksums) -> Option<Self> {
+        if !settings.use_cache || checksums.is_missing() {
+            return None;
+        }
+        let root = get_assets_cache_dir();
+        // Another repository or API may have a different release with the same tag
+        let dir = root.join(get_source_dir_name(settings)).join(get_tag_dir_name(tag));
+
+        return Some(Self { root, dir, size_limit: settings.cache_size_limit * MIB });
+    }
+
+    /// Returns the cached asset if we have it and it still matches the checksum,
+    /// corrupted ones are deleted
+    pub fn get(&self, asset: &GHAsset, checksums: &Checksums) -> Result<Option<File>, DownloadError> {
+        let expected_digest = match checksums.get_required(&asset.name)? {
+            Some(expected_digest) => expected_digest,
+            None => return Ok(None)
+        };
+        let path = self.dir.join(&asset.name);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(None)
+        };
+        let is_intact = file.metadata()?.len() == asset.size
+            && checksum::hash_file(&file)? == expected_digest;
+        if !is_intact {
+            eprintln!("The cached '{}' is corrupted, it'll be downloaded again", asset.name);
+            drop(file);
+            remove_file(&path);
+            return Ok(None);
+        }
+
+        return Ok(Some(file));
+    }
+
+    /// Moves a verified download into the cache, then deletes the oldest assets if we're over the limit
+    /// This is "best-effort", the download is deleted if it can't be cached
+    pub fn store(&self, asset: &GHAsset, download: PartialDownload) {
+        if asset.size > self.size_limit {
+            download.remove();
+            return;
+        }
+        let rv = fs::create_dir_all(&self.dir)
+            .and_then(|_| download.persist(&self.dir.join(&asset.name)));
+        if let Err(e) = rv {
+            eprintln!("Failed to cache '{}': {e}", asset.name);
+            return;
+        }
+
+        self.evict();
+    }
+
+    /// Deletes the oldest assets until the cache fits into the limit, this release is kept
+    fn evict(&self) {
+        let mut assets = list_cached_assets(&self.root);
+        let mut total_size: ContentSize = assets.iter().map(|(_, size, _)| size).sum();
+        assets.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, size, _) in assets {
+            if total_size <= self.size_limit {
+                break;
+            }
+            if path.starts_with(&self.dir) || !remove_file(&path) {
+                continue;
+            }
+            total_size -= size;
+            // These fail if there are other assets of the release (or releases from the source),
+            // which is what we want
+            if let Some(tag_dir) = path.parent() {
+                fs::remove_dir(tag_dir).ok();
+                if let Some(source_dir) = tag_dir.parent() {
+                    fs::remove_dir(source_dir).ok();
+                }
+            }
+        }
+    }
+}
+
+
+/// Returns the dir the verified assets are cached in
+pub fn get_assets_cache_dir() -> PathBuf {
+    return utils::get_cache_dir().join("releases");
+}
+
+/// Returns a dir name for the source of the releases, it's the start of the hash of the repository API URL
+fn get_source_dir_name(settings: &Settings) -> String {
+    let digest = format!("{:x}", Sha256::digest(settings.get_repo_api_url().as_bytes()));
+    return digest[..16].to_string();
+}
+
+/// Returns a dir name for the given tag, tags may have chars that aren't allowed in paths
+fn get_tag_dir_name(tag: &str) -> String {
+    let name: String = tag.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "._-+".contains(c) { c } else { '_' })
+        .collect();
+    // Don't let it be "." or ".."
+    let name = name.trim_start_matches('.');
+
+    return match name.is_empty() {
+        true => "_".to_string(),
+        false => name.to_string()
+    };
+}
+
+/// Returns the paths of the subdirs of the given dir
+fn list_subdirs(path: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new()
+    };
+
+    return entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| matches!(entry.metadata(), Ok(metadata) if metadata.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+}
+
+/// Returns the paths, sizes and modification times of all cached assets
+fn list_cached_assets(root: &Path) -> Vec<(PathBuf, ContentSize, SystemTime)> {
+    let mut assets = Vec::new();
+
+    let tag_dirs = list_subdirs(root).into_iter().flat_map(|source_dir| list_subdirs(&source_dir));
+    for tag_dir in tag_dirs {
+        let entries = match fs::read_dir(&tag_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    assets.push((entry.path(), metadata.len(), modified));
+                }
+            }
+        }
+    }
+
+    return assets;
+}
+
+/// Returns the total size of the files in the given dir
+fn get_dir_size(path: &Path) -> ContentSize {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0
+    };
+
+    return entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => get_dir_size(&entry.path()),
+                Ok(metadata) => metadata.len(),
+                Err(_) => 0
+            }
+        })
+        .sum();
+}
+
+/// Deletes a file, returns false if we couldn't
+fn remove_file(path: &Path) -> bool {
+    if let Err(e) = fs::remove_file(path) {
+        eprintln!("Failed to delete '{}': {e}", path.display());
+        return false;
+    }
+    return true;
+}
+
+/// Deletes the cached assets and the partial downloads, returns how many bytes have been freed
+pub fn clear_cache() -> Result<ContentSize, io::Error> {
+    let mut freed = 0;
+
+    for dir in [get_assets_cache_dir(), download::get_downloads_dir()] {
+        if !dir.exists() {
+            continue;
+        }
+        freed += get_dir_size(&dir);
+        fs::remove_dir_all(&dir)?;
+    }
+
+    return Ok(freed);
+}
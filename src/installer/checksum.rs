@@ -0,0 +1,162 @@
+/// The module that implements verifying downloaded assets with SHA-256
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write}
+};
+
+use ::reqwest::blocking as reqwest;
+
+use sha2::{Sha256, Digest};
+
+use crate::errors::DownloadError;
+use super::release::GHAsset;
+
+
+// The checksums and the signature are a few lines of text, anything bigger is bogus
+const MAX_TEXT_ASSET_SIZE: u64 = 1024*64;
+
+
+/// Digests of the release assets, parsed from a sha256sum-style file
+#[derive(Debug, Default)]
+pub struct Checksums {
+    digests: HashMap<String, String>,
+    // The release has no checksums at all, so there's nothing to verify the assets against
+    is_missing: bool
+}
+
+impl Checksums {
+    /// Parses the output of sha256sum, lines look like "<hex digest>  <file name>"
+    /// Invalid lines are ignored
+    pub fn parse(text: &str) -> Self {
+        let mut digests = HashMap::new();
+
+        for line in text.lines() {
+            let mut parts = line.trim().splitn(2, char::is_whitespace);
+            let digest = parts.next().unwrap_or_default();
+            // The name may be prefixed with '*' in binary mode
+            let name = parts.next().unwrap_or_default().trim_start().trim_start_matches('*');
+
+            if name.is_empty() || digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            digests.insert(name.to_string(), digest.to_lowercase());
+        }
+
+        return Self { digests, is_missing: false };
+    }
+
+    /// Returns checksums for a release that doesn't provide any, the assets are only checked by their size
+    pub fn missing() -> Self {
+        return Self { digests: HashMap::new(), is_missing: true };
+    }
+
+    /// Returns the expected digest of the given asset
+    pub fn get(&self, asset_name: &str) -> Option<&str> {
+        return self.digests.get(asset_name).map(|d| d.as_str());
+    }
+
+    /// Checks if the release has no checksums at all
+    pub fn is_missing(&self) -> bool {
+        return self.is_missing;
+    }
+
+    /// Returns the expected digest of the given asset, or an error if there's none
+    /// Returns None if the release has no checksums at all
+    pub fn get_required(&self, asset_name: &str) -> Result<Option<&str>, DownloadError> {
+        if self.is_missing {
+            return Ok(None);
+        }
+        return self.get(asset_name)
+            .map(Some)
+            .ok_or_else(|| DownloadError::MissingChecksum(asset_name.to_string()));
+    }
+}
+
+
+/// Writer that hashes everything that goes thru it
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Wraps the given writer
+    pub fn new(inner: W) -> Self {
+        return Self { inner, hasher: Sha256::new() };
+    }
+
+    /// Hashes data that has been written before, without writing it again
+    pub fn hash_existing<R: Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        return io::copy(&mut reader, &mut self.hasher);
+    }
+
+    /// Returns the hex digest of the written data
+    pub fn finalize(self) -> String {
+        return format!("{:x}", self.hasher.finalize());
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        return Ok(written);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+
+/// Returns the SHA-256 hex digest of the whole file
+pub fn hash_file(mut file: &File) -> io::Result<String> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut writer = HashingWriter::new(io::sink());
+    writer.hash_existing(file)?;
+
+    return Ok(writer.finalize());
+}
+
+
+/// Downloads a small text asset from the given URL
+fn request_text(client: &reqwest::Client, url: &str) -> Result<String, DownloadError> {
+    let resp = client.get(url).send()?;
+    let status_code = resp.status();
+    if !status_code.is_success() {
+        return Err(DownloadError::InvalidStatusCode(status_code));
+    }
+
+    // Mirrors (or GitHub) can send more than the release says, so we don't trust the size
+    let mut text = String::new();
+    resp.take(MAX_TEXT_ASSET_SIZE + 1).read_to_string(&mut text)?;
+    if text.len() as u64 > MAX_TEXT_ASSET_SIZE {
+        return Err(DownloadError::InvalidContentLen);
+    }
+
+    return Ok(text);
+}
+
+/// Downloads a small text asset, like the checksums or their signature
+/// The mirrors are tried in order if GitHub fails
+pub fn request_text_asset(client: &reqwest::Client, asset: &GHAsset) -> Result<String, DownloadError> {
+    if asset.size > MAX_TEXT_ASSET_SIZE {
+        return Err(DownloadError::InvalidContentLen);
+    }
+
+    let mut last_error = None;
+    for url in asset.get_urls() {
+        match request_text(client, url) {
+            Ok(text) => return Ok(text),
+            Err(e) => {
+                eprintln!("Failed to download '{}' from '{url}': {e}", asset.name);
+                last_error = Some(e);
+            }
+        };
+    }
+
+    // There's always at least one URL
+    return Err(last_error.unwrap());
+}
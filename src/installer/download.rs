@@ -0,0 +1,795 @@
+/// The module that implements downloading release assets
+/// Assets are downloaded in chunks by several workers at once,
+/// the chunks we got are kept between runs so the download can be resumed
+/// Parts of an asset can also be read without downloading it, see RemoteFile
+/// Transient failures are retried, a chunk continues from the last byte we've got
+/// If an URL keeps failing, we move on to the next mirror of the asset
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+        Arc,
+        Mutex
+    },
+    thread,
+    time::Duration
+};
+
+use ::reqwest::{
+    blocking as reqwest,
+    header as headers,
+    StatusCode
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    errors::DownloadError,
+    utils
+};
+use super::{
+    checksum::{self, Checksums, HashingWriter},
+    release::GHAsset,
+    reporter::{InstallEvent, Reporter},
+    retry::{self, FailedAttempt},
+    ContentSize
+};
+
+
+const PARTIAL_FILE_EXT: &str = "part";
+const STATE_FILE_EXT: &str = "part.json";
+
+// Size of the ranges we request
+const CHUNK_SIZE: ContentSize = 1024*1024*8;
+// How often we check for abort while the workers are busy
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Size of the buffer we copy the data thru
+const BUF_SIZE: usize = 1024*64;
+// Size of the blocks RemoteFile requests
+const REMOTE_BLOCK_SIZE: ContentSize = 1024*64;
+
+
+/// What we know about a partial download, it's saved next to the data
+#[derive(Serialize, Deserialize, Debug)]
+struct PartialState {
+    /// The canonical URL of the asset, even if the data is from a mirror
+    url: String,
+    size: ContentSize,
+    etag: Option<String>,
+    /// The URL the ETag is from, mirrors have their own ETags
+    #[serde(default)]
+    etag_url: Option<String>,
+    chunk_size: ContentSize,
+    /// Indices of the chunks we have
+    done_chunks: Vec<u64>,
+    written: ContentSize
+}
+
+impl PartialState {
+    /// Returns the total number of chunks
+    fn get_total_chunks(&self) -> u64 {
+        return (self.size + self.chunk_size - 1) / self.chunk_size;
+    }
+
+    /// Returns the start and the end (exclusive) of the given chunk
+    fn get_chunk_range(&self, index: u64) -> (ContentSize, ContentSize) {
+        let start = index * self.chunk_size;
+        let end = (start + self.chunk_size).min(self.size);
+        return (start, end);
+    }
+
+    /// Returns indices of the chunks we still need
+    fn get_pending_chunks(&self) -> Vec<u64> {
+        return (0..self.get_total_chunks())
+            .filter(|index| !self.done_chunks.contains(index))
+            .collect();
+    }
+
+    /// Records that we have the given chunk
+    fn mark_chunk_done(&mut self, index: u64) {
+        if self.done_chunks.contains(&index) {
+            return;
+        }
+        let (start, end) = self.get_chunk_range(index);
+        self.done_chunks.push(index);
+        self.written += end - start;
+    }
+
+    /// Sets the ETag we got from the given URL
+    fn set_etag(&mut self, url: &str, etag: Option<String>) {
+        self.etag_url = etag.as_ref().map(|_| url.to_string());
+        self.etag = etag;
+    }
+
+    /// Returns the ETag if it's from the given URL
+    fn get_etag(&self, url: &str) -> Option<&str> {
+        if self.etag_url.as_deref() != Some(url) {
+            return None;
+        }
+        return self.etag.as_deref();
+    }
+
+    /// Forgets all the data we have
+    fn reset(&mut self) {
+        self.set_etag("", None);
+        self.done_chunks.clear();
+        self.written = 0;
+    }
+
+    /// Checks if the saved state is consistent with itself
+    fn is_valid(&self) -> bool {
+        let total_chunks = self.get_total_chunks();
+        let written: ContentSize = self.done_chunks.iter()
+            .filter(|index| **index < total_chunks)
+            .map(|index| {
+                let (start, end) = self.get_chunk_range(*index);
+                end - start
+            })
+            .sum();
+        return written == self.written && self.done_chunks.len() as u64 <= total_chunks;
+    }
+}
+
+/// A download that can be resumed after the installer restarts
+#[derive(Debug)]
+pub struct PartialDownload {
+    path: PathBuf,
+    state_path: PathBuf,
+    state: PartialState,
+    file: File
+}
+
+impl PartialDownload {
+    /// Opens the partial download of the given asset in the given dir
+    /// The existing data is kept only if it's from the same URL, has the same size and we know its ETag
+    pub fn open(dir: &Path, asset: &GHAsset) -> Result<Self, io::Error> {
+        fs::create_dir_all(dir)?;
+
+        let path = dir.join(format!("{}.{PARTIAL_FILE_EXT}", asset.name));
+        let state_path = dir.join(format!("{}.{STATE_FILE_EXT}", asset.name));
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)?;
+        let file_size = file.metadata()?.len();
+
+        let mut state = PartialState {
+            url: asset.browser_download_url.clone(),
+            size: asset.size,
+            etag: None,
+            etag_url: None,
+            chunk_size: CHUNK_SIZE,
+            done_chunks: Vec::new(),
+            written: 0
+        };
+        if let Some(saved_state) = read_state(&state_path) {
+            let can_resume = saved_state.url == state.url
+                && saved_state.size == state.size
+                && saved_state.chunk_size == state.chunk_size
+                && saved_state.etag.is_some()
+                && saved_state.is_valid()
+                && file_size == state.size;
+            if can_resume {
+                state = saved_state;
+            }
+        }
+        // Drop the data if we can't use it
+        if state.written == 0 {
+            file.set_len(0)?;
+        }
+
+        return Ok(Self { path, state_path, state, file });
+    }
+
+    /// Returns the file with the data
+    pub fn get_file(&self) -> &File {
+        return &self.file;
+    }
+
+    /// Saves the state, this is "best-effort"
+    fn save_state(&self) {
+        if let Err(e) = write_state(&self.state_path, &self.state) {
+            eprintln!("Failed to save the download state: {e}");
+        }
+    }
+
+    /// Drops the data we have, but keeps the download
+    fn discard(&mut self) -> Result<(), io::Error> {
+        self.state.reset();
+        self.file.set_len(0)?;
+        self.save_state();
+        return Ok(());
+    }
+
+    /// Moves the data to the given path, it's deleted if it can't be moved
+    pub fn persist(self, path: &Path) -> Result<(), io::Error> {
+        // Windows can't move open files
+        drop(self.file);
+        let rv = fs::rename(&self.path, path);
+        if rv.is_err() {
+            fs::remove_file(&self.path).ok();
+        }
+        if let Err(e) = fs::remove_file(&self.state_path) {
+            eprintln!("Failed to delete '{}': {e}", self.state_path.display());
+        }
+
+        return rv;
+    }
+
+    /// Deletes the data, this is "best-effort"
+    pub fn remove(self) {
+        drop(self.file);
+        for path in [&self.state_path, &self.path] {
+            if let Err(e) = fs::remove_file(path) {
+                eprintln!("Failed to delete '{}': {e}", path.display());
+            }
+        }
+    }
+}
+
+
+/// Returns the dir partial downloads are kept in
+pub fn get_downloads_dir() -> PathBuf {
+    return utils::get_cache_dir().join("downloads");
+}
+
+/// Deletes the downloads in the given dir that are complete, but haven't been moved anywhere
+/// (e.g. the install has failed after downloading them), this is "best-effort"
+pub fn remove_finished_downloads(dir: &Path) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+    };
+    let state_suffix = format!(".{STATE_FILE_EXT}");
+
+    for entry in entries.flatten() {
+        let state_path = entry.path();
+        let asset_name = match entry.file_name().to_str().and_then(|name| name.strip_suffix(&state_suffix)) {
+            Some(asset_name) => asset_name.to_string(),
+            None => continue
+        };
+        let is_finished = matches!(
+            read_state(&state_path),
+            Some(state) if state.is_valid() && state.written == state.size
+        );
+        if !is_finished {
+            continue;
+        }
+        for path in [dir.join(format!("{asset_name}.{PARTIAL_FILE_EXT}")), state_path] {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("Failed to delete '{}': {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Reads the state of a partial download, returns None if there's no valid state
+fn read_state(path: &Path) -> Option<PartialState> {
+    let data = fs::read(path).ok()?;
+    return serde_json::from_slice(&data).ok();
+}
+
+/// Saves the state of a partial download
+fn write_state(path: &Path, state: &PartialState) -> Result<(), io::Error> {
+    let data = serde_json::to_vec(state)?;
+    return fs::write(path, data);
+}
+
+/// Returns the strong ETag of the response, weak ones can't be used to resume
+fn get_strong_etag(resp: &reqwest::Response) -> Option<String> {
+    return resp.headers().get(headers::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .map(|etag| etag.to_string());
+}
+
+/// Sends a download request, it times out if no data arrives for the read timeout from the settings,
+/// so a stalled connection gets retried
+fn send_request(req: reqwest::RequestBuilder) -> Result<reqwest::Response, FailedAttempt> {
+    let resp = req.send()?;
+
+    let status_code = resp.status();
+    if !status_code.is_success() {
+        return Err(FailedAttempt {
+            error: DownloadError::InvalidStatusCode(status_code),
+            retry_after: retry::get_retry_after(&resp)
+        });
+    }
+
+    return Ok(resp);
+}
+
+/// Requests the given range of the asset
+/// The server sends the whole asset if it has changed since we got the ETag, or if it doesn't support ranges
+fn request_range(
+    client: &reqwest::Client,
+    url: &str,
+    etag: Option<&str>,
+    start: ContentSize,
+    end: ContentSize
+) -> Result<reqwest::Response, FailedAttempt> {
+    let mut req = client
+        .get(url)
+        .header(headers::RANGE, format!("bytes={}-{}", start, end-1));
+    if let Some(etag) = etag {
+        req = req.header(headers::IF_RANGE, etag);
+    }
+
+    return send_request(req);
+}
+
+/// Copies the response into the writer, the offset is advanced as the data is written,
+/// so it's correct even if the copying fails midway
+fn copy_response<W: Write>(
+    resp: &mut reqwest::Response,
+    writer: &mut W,
+    offset: &mut ContentSize
+) -> Result<(), io::Error> {
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let received = match resp.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(received) => received,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        };
+        writer.write_all(&buf[..received])?;
+        *offset += received as ContentSize;
+    }
+}
+
+
+/// Events the workers send to the thread that runs the download
+enum WorkerEvent {
+    ChunkDone(u64),
+    AssetChanged,
+    Failed(DownloadError)
+}
+
+/// Everything a worker needs to download chunks
+#[derive(Clone)]
+struct WorkerContext {
+    client: reqwest::Client,
+    path: PathBuf,
+    url: String,
+    etag: Option<String>,
+    chunks: Arc<Mutex<Vec<(u64, ContentSize, ContentSize)>>>,
+    abort_flag: Arc<AtomicBool>
+}
+
+/// Downloads a chunk and writes it at its offset
+/// If the connection fails, we retry from the last byte we've got
+/// Returns false if the server has sent the whole asset instead
+fn download_chunk(ctx: &WorkerContext, start: ContentSize, end: ContentSize) -> Result<bool, DownloadError> {
+    // Each worker has its own handle so they don't share the position
+    let mut file = File::options().write(true).open(&ctx.path)?;
+    let mut offset = start;
+
+    return retry::with_retries(
+        &|| ctx.abort_flag.load(Ordering::Relaxed),
+        || {
+            let mut resp = request_range(&ctx.client, &ctx.url, ctx.etag.as_deref(), offset, end)?;
+            if resp.status() != StatusCode::PARTIAL_CONTENT {
+                return Ok(false);
+            }
+
+            file.seek(SeekFrom::Start(offset))?;
+            copy_response(&mut resp, &mut file, &mut offset)?;
+            if offset != end {
+                return Err(DownloadError::SizeMismatch(end - start, offset - start).into());
+            }
+
+            return Ok(true);
+        }
+    );
+}
+
+/// Takes chunks from the queue and downloads them until there's nothing left
+fn run_worker(ctx: WorkerContext, sender: mpsc::Sender<WorkerEvent>) {
+    while !ctx.abort_flag.load(Ordering::Relaxed) {
+        let chunk = ctx.chunks.lock().unwrap().pop();
+        let (index, start, end) = match chunk {
+            Some(chunk) => chunk,
+            None => return
+        };
+
+        let event = match download_chunk(&ctx, start, end) {
+            Ok(true) => WorkerEvent::ChunkDone(index),
+            Ok(false) => WorkerEvent::AssetChanged,
+            Err(e) => WorkerEvent::Failed(e)
+        };
+        let should_stop = !matches!(event, WorkerEvent::ChunkDone(_));
+        if sender.send(event).is_err() || should_stop {
+            return;
+        }
+    }
+}
+
+/// Downloads the given chunks using the given number of workers
+/// Returns false if the asset has changed and we need to start over
+fn download_chunks(
+    client: &reqwest::Client,
+    reporter: &dyn Reporter,
+    download: &mut PartialDownload,
+    url: &str,
+    chunks: Vec<u64>,
+    workers: usize
+) -> Result<bool, DownloadError> {
+    // Reverse it since the workers pop from the end
+    let chunks = chunks.into_iter()
+        .rev()
+        .map(|index| {
+            let (start, end) = download.state.get_chunk_range(index);
+            (index, start, end)
+        })
+        .collect();
+    let ctx = WorkerContext {
+        client: client.clone(),
+        path: download.path.clone(),
+        url: url.to_string(),
+        etag: download.state.get_etag(url).map(|etag| etag.to_string()),
+        chunks: Arc::new(Mutex::new(chunks)),
+        abort_flag: Arc::new(AtomicBool::new(false))
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let ctx = ctx.clone();
+            let sender = sender.clone();
+            thread::spawn(move || run_worker(ctx, sender))
+        })
+        .collect();
+    // Workers hold their own senders, so we know they're done when the channel disconnects
+    drop(sender);
+
+    let mut rv = Ok(true);
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(WorkerEvent::ChunkDone(index)) => {
+                download.state.mark_chunk_done(index);
+                download.save_state();
+                let pb_val = download.state.written as f64 / download.state.size as f64;
+                reporter.report(InstallEvent::Progress(pb_val));
+            },
+            Ok(WorkerEvent::AssetChanged) => {
+                if let Ok(true) = rv {
+                    rv = Ok(false);
+                }
+                ctx.abort_flag.store(true, Ordering::Relaxed);
+            },
+            Ok(WorkerEvent::Failed(e)) => {
+                if rv.is_ok() {
+                    rv = Err(e);
+                }
+                ctx.abort_flag.store(true, Ordering::Relaxed);
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {},
+            Err(mpsc::RecvTimeoutError::Disconnected) => break
+        };
+        if reporter.is_aborted() {
+            ctx.abort_flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    for handle in handles {
+        if handle.join().is_err() {
+            eprintln!("A download worker has panicked");
+        }
+    }
+    // The workers fail if they're aborted while waiting to retry
+    if reporter.is_aborted() {
+        return Ok(true);
+    }
+
+    return rv;
+}
+
+/// Writes the whole response into the file from the beginning, used when the server doesn't do ranges
+/// Returns the SHA-256 hex digest of the data, or None if we were aborted
+fn stream_to_file(
+    reporter: &dyn Reporter,
+    download: &mut PartialDownload,
+    url: &str,
+    mut resp: reqwest::Response
+) -> Result<Option<String>, DownloadError> {
+    download.state.reset();
+    download.state.set_etag(url, get_strong_etag(&resp));
+    download.file.set_len(0)?;
+    (&download.file).seek(SeekFrom::Start(0))?;
+
+    let content_size = download.state.size;
+    let mut writer = HashingWriter::new(&download.file);
+    let mut buf = vec![0; BUF_SIZE];
+    let mut total_downloaded: ContentSize = 0;
+
+    loop {
+        let received = match resp.read(&mut buf) {
+            Ok(0) => break,
+            Ok(received) => received,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into())
+        };
+        writer.write_all(&buf[..received])?;
+        total_downloaded += received as ContentSize;
+
+        // Record the chunks we've got, so we can resume with ranges if the server starts supporting them
+        let prev_written = download.state.written;
+        while download.state.written < download.state.size {
+            let index = download.state.done_chunks.len() as u64;
+            let (_, end) = download.state.get_chunk_range(index);
+            if end > total_downloaded {
+                break;
+            }
+            download.state.mark_chunk_done(index);
+        }
+        if download.state.written != prev_written {
+            download.save_state();
+            let pb_val = total_downloaded as f64 / content_size as f64;
+            reporter.report(InstallEvent::Progress(pb_val));
+        }
+
+        if reporter.is_aborted() {
+            return Ok(None);
+        }
+    }
+
+    if total_downloaded != content_size {
+        return Err(DownloadError::SizeMismatch(content_size, total_downloaded));
+    }
+    reporter.report(InstallEvent::Progress(1.0));
+
+    return Ok(Some(writer.finalize()));
+}
+
+/// Downloads the whole asset in one stream, used when the server doesn't do ranges
+/// Failed attempts restart from the beginning, the given response is used for the first attempt
+fn download_single_stream(
+    client: &reqwest::Client,
+    reporter: &dyn Reporter,
+    download: &mut PartialDownload,
+    url: &str,
+    resp: Option<reqwest::Response>
+) -> Result<Option<String>, DownloadError> {
+    let mut resp = resp;
+
+    return retry::with_retries(
+        &|| reporter.is_aborted(),
+        || {
+            let resp = match resp.take() {
+                Some(resp) => resp,
+                None => send_request(client.get(url))?
+            };
+            return Ok(stream_to_file(reporter, download, url, resp)?);
+        }
+    );
+}
+
+/// Downloads the data from the given URL into the given partial download, resuming from where it has stopped
+/// Returns the SHA-256 hex digest of the data, or None if we were aborted
+/// The amount of data is verified to match the content size
+fn download_to_file(
+    client: &reqwest::Client,
+    reporter: &dyn Reporter,
+    download: &mut PartialDownload,
+    url: &str,
+    workers: usize
+) -> Result<Option<String>, DownloadError> {
+    let content_size = download.state.size;
+    let pb_val = download.state.written as f64 / content_size as f64;
+    reporter.report(InstallEvent::Progress(pb_val));
+
+    if reporter.is_aborted() {
+        return Ok(None);
+    }
+
+    let pending_chunks = download.state.get_pending_chunks();
+    if !pending_chunks.is_empty() {
+        if download.state.written != 0 {
+            eprintln!("Resuming the download from {} bytes", download.state.written);
+        }
+
+        // Request the first byte we need, this tells us if the server does ranges and if the asset has changed
+        let (start, _) = download.state.get_chunk_range(pending_chunks[0]);
+        let resp = retry::with_retries(
+            &|| reporter.is_aborted(),
+            || request_range(client, url, download.state.get_etag(url), start, start+1)
+        )?;
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            if download.state.written != 0 {
+                eprintln!("The asset has changed, restarting the download");
+            }
+            return download_single_stream(client, reporter, download, url, Some(resp));
+        }
+        // Data from another mirror is kept, the checksum tells us if it doesn't match
+        if download.state.get_etag(url).is_none() {
+            download.state.set_etag(url, get_strong_etag(&resp));
+        }
+        drop(resp);
+
+        // So the workers can write at any offset
+        download.file.set_len(content_size)?;
+
+        let workers = workers.max(1).min(pending_chunks.len());
+        let is_unchanged = download_chunks(client, reporter, download, url, pending_chunks, workers)?;
+        if !is_unchanged {
+            eprintln!("The asset has changed, restarting the download");
+            return download_single_stream(client, reporter, download, url, None);
+        }
+
+        if reporter.is_aborted() {
+            return Ok(None);
+        }
+    }
+
+    if download.state.written != content_size {
+        return Err(DownloadError::SizeMismatch(content_size, download.state.written));
+    }
+
+    return Ok(Some(checksum::hash_file(&download.file)?));
+}
+
+/// Downloads a release asset into the given dir and verifies its size and checksum
+/// The mirrors are tried in order, the data is always verified against the canonical asset
+/// Interrupted downloads are resumed, corrupted ones are deleted
+pub fn download_asset(
+    client: &reqwest::Client,
+    reporter: &dyn Reporter,
+    dir: &Path,
+    asset: &GHAsset,
+    checksums: &Checksums,
+    workers: usize
+) -> Result<PartialDownload, DownloadError> {
+    // Check this first so we don't download for nothing
+    let expected_digest = checksums.get_required(&asset.name)?;
+
+    let mut download = PartialDownload::open(dir, asset)?;
+    let mut last_error = None;
+
+    let mut urls = asset.get_urls();
+    // Without the checksums there's nothing to verify a mirror against, so GitHub's error is final
+    if expected_digest.is_none() {
+        urls.truncate(1);
+    }
+    for (i, url) in urls.into_iter().enumerate() {
+        if i != 0 {
+            eprintln!("Trying mirror '{url}'");
+        }
+        let rv = download_to_file(client, reporter, &mut download, url, workers);
+        // Errors don't matter if the user doesn't want to continue
+        if reporter.is_aborted() {
+            return Ok(download);
+        }
+        let error = match rv {
+            Ok(Some(digest)) if expected_digest.map_or(true, |expected| digest == expected) => return Ok(download),
+            Ok(Some(_)) => DownloadError::ChecksumMismatch(asset.name.clone()),
+            Ok(None) => return Ok(download),
+            Err(e) => e
+        };
+        eprintln!("Failed to download '{}' from '{url}': {error}", asset.name);
+
+        // Corrupted data is no good for the next mirror either
+        if matches!(error, DownloadError::SizeMismatch(..) | DownloadError::ChecksumMismatch(_)) {
+            download.discard()?;
+        }
+        last_error = Some(error);
+    }
+
+    // There's always at least one URL
+    let error = last_error.unwrap();
+    if matches!(error, DownloadError::SizeMismatch(..) | DownloadError::ChecksumMismatch(_)) {
+        download.remove();
+    }
+
+    return Err(error);
+}
+
+
+/// A release asset that's read over the network, only the blocks that are read get downloaded
+/// This is how we read the central directory of an archive without downloading all of it
+pub struct RemoteFile<'a> {
+    client: &'a reqwest::Client,
+    asset: &'a GHAsset,
+    pos: ContentSize,
+    /// The blocks we've got, by their index
+    blocks: HashMap<u64, Vec<u8>>
+}
+
+impl<'a> RemoteFile<'a> {
+    /// Creates a new RemoteFile, nothing is requested until it's read
+    pub fn new(client: &'a reqwest::Client, asset: &'a GHAsset) -> Self {
+        return Self { client, asset, pos: 0, blocks: HashMap::new() };
+    }
+
+    /// Downloads the block with the given index, the mirrors are tried in order if GitHub fails
+    fn request_block(&self, index: u64) -> Result<Vec<u8>, DownloadError> {
+        let start = index * REMOTE_BLOCK_SIZE;
+        let end = (start + REMOTE_BLOCK_SIZE).min(self.asset.size);
+
+        let mut last_error = None;
+        for url in self.asset.get_urls() {
+            let rv = retry::with_retries(
+                &|| false,
+                || {
+                    let mut resp = request_range(self.client, url, None, start, end)?;
+                    // We don't want the whole asset
+                    if resp.status() != StatusCode::PARTIAL_CONTENT {
+                        return Err(DownloadError::RangeNotSupported.into());
+                    }
+
+                    let mut block = Vec::new();
+                    let mut received = 0;
+                    copy_response(&mut resp, &mut block, &mut received)?;
+                    if received != end - start {
+                        return Err(DownloadError::SizeMismatch(end - start, received).into());
+                    }
+
+                    return Ok(block);
+                }
+            );
+            match rv {
+                Ok(block) => return Ok(block),
+                Err(e) => {
+                    eprintln!("Failed to read '{}' from '{url}': {e}", self.asset.name);
+                    last_error = Some(e);
+                }
+            };
+        }
+
+        // There's always at least one URL
+        return Err(last_error.unwrap());
+    }
+}
+
+impl Read for RemoteFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.asset.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.pos / REMOTE_BLOCK_SIZE;
+        if !self.blocks.contains_key(&index) {
+            let block = self.request_block(index)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.blocks.insert(index, block);
+        }
+        let block = &self.blocks[&index];
+
+        let offset = (self.pos - index * REMOTE_BLOCK_SIZE) as usize;
+        let len = buf.len().min(block.len() - offset);
+        buf[..len].copy_from_slice(&block[offset..offset + len]);
+        self.pos += len as ContentSize;
+
+        return Ok(len);
+    }
+}
+
+impl Seek for RemoteFile<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => checked_add_signed(self.asset.size, offset),
+            SeekFrom::Current(offset) => checked_add_signed(self.pos, offset)
+        };
+        self.pos = new_pos.ok_or_else(
+            || io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position")
+        )?;
+
+        return Ok(self.pos);
+    }
+}
+
+/// Adds a signed offset to a position, returns None if the result would be negative
+fn checked_add_signed(pos: u64, offset: i64) -> Option<u64> {
+    return match offset < 0 {
+        true => pos.checked_sub(offset.unsigned_abs()),
+        false => pos.checked_add(offset as u64)
+    };
+}
@@ -0,0 +1,444 @@
+/// The module that implements dry runs, they find out what an install would change without changing anything
+/// Only the central directories of the archives are read, so nothing big gets downloaded
+
+use std::{
+    fmt,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf
+};
+
+use zip::result::ZipError;
+
+use crate::errors::{ExtractionError, InstallError};
+use super::{
+    cache::ReleaseCache,
+    download::RemoteFile,
+    find_stale_game_items,
+    get_component_dir,
+    get_entry_path,
+    get_game_dir,
+    get_spritepacks_dir,
+    get_verified_checksums,
+    join_relative_path,
+    manifest,
+    network,
+    release,
+    to_manifest_path,
+    ContentSize,
+    InstallOptions
+};
+
+
+const EOCD_SIGNATURE: u32 = 0x06054b50;
+const EOCD_SIZE: usize = 22;
+const MAX_COMMENT_SIZE: u64 = 0xFFFF;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const CENTRAL_HEADER_SIZE: usize = 46;
+// The names of the entries are UTF-8 if this flag is set
+const UTF8_FLAG: u16 = 1 << 11;
+// Sizes and offsets with this value are in the ZIP64 records
+const ZIP64_MARKER: u32 = 0xFFFFFFFF;
+
+const MIB: f64 = 1024.0*1024.0;
+
+
+/// What an install would do
+#[derive(Debug, Default)]
+pub struct InstallPlan {
+    /// The DDLC dir
+    pub extraction_dir: PathBuf,
+    /// Tag of the release, None if it's installed from local archives
+    pub release_tag: Option<String>,
+    /// Old files that get removed and aren't replaced
+    pub deleted_files: Vec<PathBuf>,
+    /// Existing files that get replaced
+    pub overwritten_files: Vec<PathBuf>,
+    pub created_files: Vec<PathBuf>,
+    /// Where the spritepacks go, None if they aren't installed
+    pub spritepacks_dir: Option<PathBuf>,
+    /// How much would be downloaded, cached assets don't count
+    pub download_size: ContentSize,
+    /// How much the extracted files take
+    pub write_size: ContentSize
+}
+
+impl InstallPlan {
+    /// Creates an empty plan, the old files to remove are already in the deleted files
+    fn new(options: &InstallOptions, release_tag: Option<String>) -> Self {
+        let game_dir = get_game_dir(&options.extraction_dir);
+        let deleted_files = find_stale_game_items(&game_dir)
+            .into_iter()
+            .filter_map(|item| join_relative_path(&game_dir, &item.rel_path))
+            .collect();
+        let spritepacks_dir = match options.install_spr {
+            true => Some(get_spritepacks_dir(&options.extraction_dir)),
+            false => None
+        };
+
+        return Self {
+            extraction_dir: options.extraction_dir.clone(),
+            release_tag,
+            deleted_files,
+            spritepacks_dir,
+            ..Self::default()
+        };
+    }
+
+    /// Adds the files of an archive of the given component to the plan
+    fn add_archive<R: Read + Seek>(&mut self, component: &str, reader: &mut R) -> Result<(), InstallError> {
+        let root = get_component_dir(&self.extraction_dir, component)
+            .ok_or_else(|| ExtractionError::UnsafeFilepath(component.to_string()))?;
+
+        for entry in read_central_directory(reader)? {
+            let file_path = match get_entry_path(&entry.name)? {
+                Some(path) if !entry.is_dir => path,
+                _ => continue
+            };
+            let path = join_relative_path(&root, &to_manifest_path(&file_path))
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(entry.name.clone()))?;
+            self.write_size += entry.size;
+
+            // The old files the archive has are replaced rather than deleted
+            let deleted_len = self.deleted_files.len();
+            self.deleted_files.retain(|deleted_path| *deleted_path != path);
+            if path.is_file() || self.deleted_files.len() != deleted_len {
+                self.overwritten_files.push(path);
+            }
+            else {
+                self.created_files.push(path);
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+impl fmt::Display for InstallPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Installing into '{}' would:", self.extraction_dir.display())?;
+        match self.release_tag {
+            Some(ref tag) => writeln!(f, "- install the release {tag}")?,
+            None => writeln!(f, "- install the local archives")?
+        };
+        match self.spritepacks_dir {
+            Some(ref path) => writeln!(f, "- install the spritepacks into '{}'", path.display())?,
+            None => writeln!(f, "- not install the spritepacks")?
+        };
+        writeln!(
+            f,
+            "- download {:.1} MiB and write {:.1} MiB",
+            self.download_size as f64 / MIB,
+            self.write_size as f64 / MIB
+        )?;
+
+        let lists = [
+            ("delete", &self.deleted_files),
+            ("overwrite", &self.overwritten_files),
+            ("create", &self.created_files)
+        ];
+        for (action, paths) in lists {
+            writeln!(f, "\nFiles to {action} ({}):", paths.len())?;
+            for path in paths {
+                writeln!(f, "    {}", path.display())?;
+            }
+        }
+
+        return write!(f, "\nDeleted and overwritten files are moved into a backup, unless they're from a previous install");
+    }
+}
+
+
+/// An entry of the central directory of an archive
+struct ArchiveEntry {
+    name: String,
+    /// Uncompressed size
+    size: ContentSize,
+    is_dir: bool
+}
+
+/// Reads a little endian u16 at the given offset
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    return u16::from_le_bytes([data[offset], data[offset + 1]]);
+}
+
+/// Reads a little endian u32 at the given offset
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+}
+
+/// Reads the central directory of a zip archive, this doesn't touch the file data
+/// NOTE: the zip crate reads the local header of every file it opens, which would mean
+/// downloading most of a remote archive, so we parse the central directory ourselves
+fn read_central_directory<R: Read + Seek>(reader: &mut R) -> Result<Vec<ArchiveEntry>, ExtractionError> {
+    let invalid_archive = |msg| ExtractionError::ArchiveError(ZipError::InvalidArchive(msg));
+
+    // The end of central directory record is at the end, followed by the comment
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let tail_size = file_size.min(EOCD_SIZE as u64 + MAX_COMMENT_SIZE);
+    reader.seek(SeekFrom::Start(file_size - tail_size))?;
+    let mut tail = vec![0; tail_size as usize];
+    reader.read_exact(&mut tail)?;
+
+    // The comment can have the signature in it too, so the comment length has to match as well
+    let eocd_pos = (0..tail.len().saturating_sub(EOCD_SIZE - 1))
+        .rev()
+        .find(|pos| {
+            read_u32(&tail, *pos) == EOCD_SIGNATURE
+            && read_u16(&tail, *pos + 20) as usize == tail.len() - *pos - EOCD_SIZE
+        })
+        .ok_or_else(|| invalid_archive("Could not find central directory end"))?;
+    let entries_count = read_u16(&tail, eocd_pos + 10);
+    let directory_size = read_u32(&tail, eocd_pos + 12);
+    let directory_offset = read_u32(&tail, eocd_pos + 16);
+    if directory_size == ZIP64_MARKER || directory_offset == ZIP64_MARKER {
+        return Err(invalid_archive("ZIP64 archives can't be previewed"));
+    }
+
+    // Both come from the archive, so they're checked before we allocate anything with them
+    let eocd_offset = file_size - tail_size + eocd_pos as u64;
+    if directory_offset as u64 + directory_size as u64 > eocd_offset {
+        return Err(invalid_archive("Central directory is out of bounds"));
+    }
+    if entries_count as u64 * CENTRAL_HEADER_SIZE as u64 > directory_size as u64 {
+        return Err(invalid_archive("Central directory is too small for its entries"));
+    }
+
+    reader.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0; directory_size as usize];
+    reader.read_exact(&mut directory)?;
+
+    let mut entries = Vec::with_capacity(entries_count as usize);
+    let mut pos = 0;
+    while pos < directory.len() {
+        if pos + CENTRAL_HEADER_SIZE > directory.len() || read_u32(&directory, pos) != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid_archive("Invalid central directory header"));
+        }
+        let flags = read_u16(&directory, pos + 8);
+        let size = read_u32(&directory, pos + 24);
+        let name_len = read_u16(&directory, pos + 28) as usize;
+        let extra_len = read_u16(&directory, pos + 30) as usize;
+        let comment_len = read_u16(&directory, pos + 32) as usize;
+        if size == ZIP64_MARKER {
+            return Err(invalid_archive("ZIP64 archives can't be previewed"));
+        }
+
+        let name_start = pos + CENTRAL_HEADER_SIZE;
+        let name = directory.get(name_start..name_start + name_len)
+            .ok_or_else(|| invalid_archive("Invalid central directory header"))?;
+        // Anything that isn't UTF-8 is CP437, it's the same for the ASCII names we have
+        let name = match flags & UTF8_FLAG {
+            0 => String::from_utf8_lossy(name).to_string(),
+            _ => String::from_utf8(name.to_vec()).map_err(|_| invalid_archive("Invalid file name"))?
+        };
+
+        entries.push(
+            ArchiveEntry { is_dir: name.ends_with('/'), name, size: size as ContentSize }
+        );
+        pos = name_start + name_len + extra_len + comment_len;
+    }
+
+    return Ok(entries);
+}
+
+/// Returns how much the files of an archive take once extracted, only the central directory is read
+pub(super) fn get_extracted_size<R: Read + Seek>(reader: &mut R) -> Result<ContentSize, ExtractionError> {
+    let size = read_central_directory(reader)?
+        .iter()
+        .map(|entry| entry.size)
+        .sum();
+
+    return Ok(size);
+}
+
+
+/// Finds out what installing with the given options would change, nothing is changed
+/// The release metadata is fetched, but only the central directories of the archives are downloaded
+pub fn plan_install(options: &InstallOptions) -> Result<InstallPlan, InstallError> {
+    if let Some(ref archives) = options.local_archives {
+        let mut plan = InstallPlan::new(options, None);
+        plan.add_archive(manifest::COMPONENT_GAME, &mut File::open(&archives.game)?)?;
+        if options.install_spr {
+            let path = archives.spritepacks.as_ref().ok_or(InstallError::MissingArchive("spritepacks"))?;
+            plan.add_archive(manifest::COMPONENT_SPRITEPACKS, &mut File::open(path)?)?;
+        }
+        return Ok(plan);
+    }
+
+    options.settings.validate()?;
+    let client = network::build_client(&options.settings)?;
+    let data = release::get_release_data(&client, &options.settings, &options.release)?;
+    // Would be refused anyway
+    let checksums = get_verified_checksums(&client, &options.settings, &data, options.allow_unsigned)?;
+    let cache = ReleaseCache::open(&options.settings, &data.version, &checksums);
+
+    let mut plan = InstallPlan::new(options, Some(data.version.clone()));
+    let mut assets = vec![(manifest::COMPONENT_GAME, &data.def_ver_asset)];
+    if options.install_spr {
+        assets.push((manifest::COMPONENT_SPRITEPACKS, &data.spr_asset));
+    }
+    for (component, asset) in assets {
+        let cached_file = match cache {
+            Some(ref cache) => cache.get(asset, &checksums)?,
+            None => None
+        };
+        match cached_file {
+            Some(mut file) => plan.add_archive(component, &mut file)?,
+            None => {
+                plan.download_size += asset.size;
+                plan.add_archive(component, &mut RemoteFile::new(&client, asset))?;
+            }
+        };
+    }
+
+    return Ok(plan);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{result::ZipError, write::FileOptions, ZipWriter};
+
+    use super::{read_central_directory, ArchiveEntry, CENTRAL_HEADER_SIGNATURE, EOCD_SIGNATURE, UTF8_FLAG};
+    use crate::errors::ExtractionError;
+
+
+    /// Writes an archive with the given files and their contents, names ending with '/' are dirs
+    fn write_archive(files: &[(&str, &[u8])], comment: Option<&str>) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            if name.ends_with('/') {
+                writer.add_directory(*name, FileOptions::default()).unwrap();
+                continue;
+            }
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        if let Some(comment) = comment {
+            writer.set_comment(comment);
+        }
+
+        return writer.finish().unwrap().into_inner();
+    }
+
+    /// Returns the offset of the last occurrence of the given signature
+    fn find_signature(data: &[u8], signature: u32) -> usize {
+        return data.windows(4)
+            .rposition(|window| window == signature.to_le_bytes())
+            .unwrap();
+    }
+
+    fn read(data: &[u8]) -> Result<Vec<ArchiveEntry>, ExtractionError> {
+        return read_central_directory(&mut Cursor::new(data));
+    }
+
+    /// Checks the archive is refused with an error that says the given thing
+    fn assert_invalid(data: &[u8], expected_msg: &str) {
+        match read(data) {
+            Err(ExtractionError::ArchiveError(ZipError::InvalidArchive(msg))) => {
+                assert!(msg.contains(expected_msg), "unexpected error: {msg}");
+            },
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("the archive hasn't been refused")
+        };
+    }
+
+    #[test]
+    fn test_read_normal_archive() {
+        let data = write_archive(
+            &[("JN/", b""), ("JN/game/", b""), ("JN/game/script.rpy", b"label start:\n"), ("JN/DDLC.sh", b"#!/bin/sh\n")],
+            None
+        );
+
+        let entries = read(&data).unwrap();
+        let entries: Vec<(&str, u64, bool)> = entries.iter()
+            .map(|entry| (entry.name.as_str(), entry.size, entry.is_dir))
+            .collect();
+        assert_eq!(
+            entries,
+            [("JN/", 0, true), ("JN/game/", 0, true), ("JN/game/script.rpy", 13, false), ("JN/DDLC.sh", 10, false)]
+        );
+    }
+
+    #[test]
+    fn test_read_archive_with_comment() {
+        // The comment even has the signature of the end record in it
+        let mut comment = String::from("Just Natsuki ");
+        comment.push_str(&String::from_utf8_lossy(&EOCD_SIGNATURE.to_le_bytes()));
+        comment.push_str(" is more than a mod, it's a whole new experience");
+        let data = write_archive(&[("JN/game/script.rpy", b"label start:\n")], Some(&comment));
+
+        let entries = read(&data).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "JN/game/script.rpy");
+        assert_eq!(entries[0].size, 13);
+    }
+
+    #[test]
+    fn test_read_truncated_archive() {
+        let data = write_archive(&[("JN/game/script.rpy", b"label start:\n")], None);
+
+        // Without the end record
+        assert_invalid(&data[..data.len() - 10], "Could not find central directory end");
+        // Without the start of the central directory
+        let directory_pos = find_signature(&data, CENTRAL_HEADER_SIGNATURE);
+        let mut truncated = data[..directory_pos].to_vec();
+        truncated.extend_from_slice(&data[directory_pos + 10..]);
+        assert_invalid(&truncated, "Central directory is out of bounds");
+        // Without the local files, the offset points past the directory
+        assert_invalid(&data[20..], "Central directory is out of bounds");
+    }
+
+    #[test]
+    fn test_read_out_of_bounds_directory() {
+        let data = write_archive(&[("JN/game/script.rpy", b"label start:\n")], None);
+        let eocd_pos = find_signature(&data, EOCD_SIGNATURE);
+
+        let mut broken = data.clone();
+        broken[eocd_pos + 16..eocd_pos + 20].copy_from_slice(&0x7FFF_0000u32.to_le_bytes());
+        assert_invalid(&broken, "Central directory is out of bounds");
+
+        let mut broken = data.clone();
+        broken[eocd_pos + 12..eocd_pos + 16].copy_from_slice(&0x7FFF_0000u32.to_le_bytes());
+        assert_invalid(&broken, "Central directory is out of bounds");
+
+        let mut broken = data;
+        broken[eocd_pos + 10..eocd_pos + 12].copy_from_slice(&1000u16.to_le_bytes());
+        assert_invalid(&broken, "Central directory is too small for its entries");
+    }
+
+    #[test]
+    fn test_read_zip64_archive() {
+        let data = write_archive(&[("JN/game/script.rpy", b"label start:\n")], None);
+        let eocd_pos = find_signature(&data, EOCD_SIGNATURE);
+        let header_pos = find_signature(&data, CENTRAL_HEADER_SIGNATURE);
+
+        for offset in [eocd_pos + 12, eocd_pos + 16, header_pos + 24] {
+            let mut zip64 = data.clone();
+            zip64[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert_invalid(&zip64, "ZIP64 archives can't be previewed");
+        }
+    }
+
+    #[test]
+    fn test_read_non_utf8_names() {
+        // Non-ASCII names are written as UTF-8, with the flag set
+        let data = write_archive(&[("JN/game/café.txt", b"")], None);
+        assert_eq!(read(&data).unwrap()[0].name, "JN/game/café.txt");
+
+        // CP437 names don't have the flag, 0x82 is 'é' there
+        let data = write_archive(&[("JN/game/cafX.txt", b"")], None);
+        let header_pos = find_signature(&data, CENTRAL_HEADER_SIGNATURE);
+        let name_pos = header_pos + 46 + "JN/game/caf".len();
+        let mut cp437 = data;
+        cp437[name_pos] = 0x82;
+        assert_eq!(read(&cp437).unwrap()[0].name, "JN/game/caf\u{FFFD}.txt");
+
+        // The same bytes with the UTF-8 flag are broken
+        let flags = u16::from_le_bytes([cp437[header_pos + 8], cp437[header_pos + 9]]) | UTF8_FLAG;
+        cp437[header_pos + 8..header_pos + 10].copy_from_slice(&flags.to_le_bytes());
+        assert_invalid(&cp437, "Invalid file name");
+    }
+}
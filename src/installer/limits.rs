@@ -0,0 +1,200 @@
+/// The module with the limits for extracting archives
+/// They keep a broken or malicious archive (e.g. a zip bomb from a mirror) from filling the disk
+
+use std::io::{Read, Seek};
+
+use zip::ZipArchive;
+
+use crate::{errors::ExtractionError, settings::Settings};
+use super::ContentSize;
+
+
+const MIB: ContentSize = 1024*1024;
+// Small files can compress extremely well (e.g. all spaces), we only check the ratio of the bigger ones
+const RATIO_CHECK_MIN_SIZE: ContentSize = MIB;
+
+
+/// Hard limits for an archive we extract
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractionLimits {
+    /// How many entries an archive can have
+    pub max_entries: usize,
+    /// How many bytes the extracted files can take
+    pub max_size: ContentSize,
+    /// How many times an entry can be bigger than its compressed data
+    pub max_ratio: ContentSize
+}
+
+impl ExtractionLimits {
+    /// Creates the limits from the settings
+    pub fn new(settings: &Settings) -> Self {
+        return Self {
+            max_entries: settings.max_archive_entries,
+            max_size: settings.max_extracted_size.saturating_mul(MIB),
+            max_ratio: settings.max_compression_ratio
+        };
+    }
+
+    /// Checks the central directory of the archive against the limits, this doesn't extract anything
+    /// NOTE: the sizes in there can lie, so the extraction must still check what it actually writes
+    pub fn check_archive<R: Read + Seek>(&self, archive: &mut ZipArchive<R>) -> Result<(), ExtractionError> {
+        if archive.len() > self.max_entries {
+            return Err(ExtractionError::TooManyEntries(archive.len(), self.max_entries));
+        }
+
+        let mut total_size: ContentSize = 0;
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i)?;
+            self.check_entry(file.name(), file.size(), file.compressed_size())?;
+            total_size = total_size.saturating_add(file.size());
+        }
+        if total_size > self.max_size {
+            return Err(ExtractionError::TooLarge(total_size, self.max_size));
+        }
+
+        return Ok(());
+    }
+
+    /// Checks the compression ratio of an entry with the given sizes
+    fn check_entry(&self, name: &str, size: ContentSize, compressed_size: ContentSize) -> Result<(), ExtractionError> {
+        if size < RATIO_CHECK_MIN_SIZE {
+            return Ok(());
+        }
+        if size / compressed_size.max(1) > self.max_ratio {
+            return Err(ExtractionError::SuspiciousCompression(name.to_string()));
+        }
+
+        return Ok(());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io::{Cursor, Write},
+        path::PathBuf,
+        process
+    };
+
+    use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+    use super::{ExtractionLimits, MIB, RATIO_CHECK_MIN_SIZE};
+    use crate::{
+        errors::ExtractionError,
+        installer::{extract_archive, reporter::{InstallEvent, Reporter}},
+        settings::Settings
+    };
+
+
+    struct NullReporter;
+
+    impl Reporter for NullReporter {
+        fn report(&self, _event: InstallEvent) {}
+    }
+
+    /// Returns an empty dir for a test
+    fn get_test_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("jn-installer-test-{}-{name}", process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+
+        return path;
+    }
+
+    /// Writes an archive with the given files and their sizes, they're all zeros, so they compress very well
+    fn write_archive(files: &[(&str, usize)], method: CompressionMethod) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, size) in files {
+            writer.start_file(format!("JN/{name}"), FileOptions::default().compression_method(method)).unwrap();
+            writer.write_all(&vec![0; *size]).unwrap();
+        }
+
+        return writer.finish().unwrap().into_inner();
+    }
+
+    fn check(limits: &ExtractionLimits, data: Vec<u8>) -> Result<(), ExtractionError> {
+        return limits.check_archive(&mut ZipArchive::new(Cursor::new(data)).unwrap());
+    }
+
+    fn get_limits() -> ExtractionLimits {
+        return ExtractionLimits { max_entries: 10, max_size: 4*MIB, max_ratio: 100 };
+    }
+
+    #[test]
+    fn test_too_many_entries() {
+        let limits = ExtractionLimits { max_entries: 3, ..get_limits() };
+        let files = [("a", 1), ("b", 1), ("c", 1)];
+        assert!(check(&limits, write_archive(&files, CompressionMethod::Stored)).is_ok());
+
+        let files = [("a", 1), ("b", 1), ("c", 1), ("d", 1)];
+        let rv = check(&limits, write_archive(&files, CompressionMethod::Stored));
+        assert!(matches!(rv, Err(ExtractionError::TooManyEntries(4, 3))), "{rv:?}");
+    }
+
+    #[test]
+    fn test_too_large() {
+        let limits = ExtractionLimits { max_size: 1000, ..get_limits() };
+        assert!(check(&limits, write_archive(&[("a", 500), ("b", 500)], CompressionMethod::Stored)).is_ok());
+
+        let rv = check(&limits, write_archive(&[("a", 500), ("b", 501)], CompressionMethod::Stored));
+        assert!(matches!(rv, Err(ExtractionError::TooLarge(1001, 1000))), "{rv:?}");
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let limits = get_limits();
+        let size = RATIO_CHECK_MIN_SIZE as usize;
+
+        // Small files aren't checked, however well they're compressed
+        assert!(check(&limits, write_archive(&[("small", size - 1)], CompressionMethod::Deflated)).is_ok());
+        let rv = check(&limits, write_archive(&[("big", size)], CompressionMethod::Deflated));
+        assert!(matches!(rv, Err(ExtractionError::SuspiciousCompression(ref name)) if name == "JN/big"), "{rv:?}");
+        // Files that aren't compressed that well are fine
+        assert!(check(&limits, write_archive(&[("big", size)], CompressionMethod::Stored)).is_ok());
+
+        assert!(limits.check_entry("big", RATIO_CHECK_MIN_SIZE, RATIO_CHECK_MIN_SIZE / 100).is_ok());
+        assert!(limits.check_entry("big", RATIO_CHECK_MIN_SIZE, RATIO_CHECK_MIN_SIZE / 101).is_err());
+        assert!(limits.check_entry("small", RATIO_CHECK_MIN_SIZE - 1, 1).is_ok());
+        assert!(limits.check_entry("empty", RATIO_CHECK_MIN_SIZE, 0).is_err());
+    }
+
+    #[test]
+    fn test_extract_checks_limits_first() {
+        let dir = get_test_dir("limits");
+        let archive_path = dir.join("archive.zip");
+        let files: Vec<(String, usize)> = (0..5).map(|i| (format!("file{i}"), 1)).collect();
+        let files: Vec<(&str, usize)> = files.iter().map(|(name, size)| (name.as_str(), *size)).collect();
+        fs::write(&archive_path, write_archive(&files, CompressionMethod::Stored)).unwrap();
+
+        let settings = Settings { max_archive_entries: 4, ..Settings::default() };
+        let destination = dir.join("out");
+        let rv = extract_archive(&NullReporter, &File::open(&archive_path).unwrap(), &destination, &settings);
+        assert!(matches!(rv, Err(ExtractionError::TooManyEntries(5, 4))), "{rv:?}");
+        assert!(!destination.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_checks_written_size() {
+        let dir = get_test_dir("size-mismatch");
+        let archive_path = dir.join("archive.zip");
+        let mut data = write_archive(&[("game/script.rpy", 100)], CompressionMethod::Stored);
+        // The central directory says the file is smaller than its data, so the limits can't see it
+        let header_pos = data.windows(4).rposition(|window| window == [0x50, 0x4b, 0x01, 0x02]).unwrap();
+        data[header_pos + 24..header_pos + 28].copy_from_slice(&10u32.to_le_bytes());
+        fs::write(&archive_path, data).unwrap();
+
+        let destination = dir.join("out");
+        let rv = extract_archive(&NullReporter, &File::open(&archive_path).unwrap(), &destination, &Settings::default());
+        assert!(matches!(rv, Err(ExtractionError::SizeMismatch(ref name)) if name == "JN/game/script.rpy"), "{rv:?}");
+        assert!(fs::metadata(destination.join("game/script.rpy")).unwrap().len() <= 11);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
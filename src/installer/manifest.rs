@@ -0,0 +1,127 @@
+/// The module that implements the manifest of an install
+/// It records what the installer has put on disk, so we can tell what's installed later
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    errors::InstallError,
+    utils
+};
+use super::{backup::Backup, ContentSize};
+
+
+/// Name of the manifest file, it's kept in the game dir of DDLC
+pub const MANIFEST_FILE_NAME: &str = "jn_install_manifest.json";
+
+// Names of the components
+pub const COMPONENT_GAME: &str = "game";
+pub const COMPONENT_SPRITEPACKS: &str = "spritepacks";
+
+
+/// A file we've extracted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledFile {
+    /// Path relative to the dir of the component, with '/' separators
+    pub path: String,
+    pub size: ContentSize,
+    /// SHA-256 hex digest
+    pub sha256: String
+}
+
+/// A part of the mod we've installed from an archive
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledComponent {
+    /// One of the COMPONENT_* names
+    pub name: String,
+    /// Name of the archive it's from
+    pub archive: String,
+    pub files: Vec<InstalledFile>
+}
+
+/// The record of an install
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallManifest {
+    pub installer_version: String,
+    /// Tag of the release, None if it's been installed from local archives
+    pub release_tag: Option<String>,
+    pub release_name: Option<String>,
+    /// When the install has finished, in seconds since the Unix epoch
+    pub installed_at: u64,
+    pub components: Vec<InstalledComponent>,
+    /// Backups of the files the installs have replaced, oldest first
+    #[serde(default)]
+    pub backups: Vec<Backup>
+}
+
+impl InstallManifest {
+    /// Creates an empty manifest for the given release
+    pub fn new(release_tag: Option<String>, release_name: Option<String>) -> Self {
+        return Self {
+            installer_version: crate::VERSION.unwrap_or(crate::DEF_VERSION).to_string(),
+            release_tag,
+            release_name,
+            installed_at: 0,
+            components: Vec::new(),
+            backups: Vec::new()
+        };
+    }
+
+    /// Returns the path to the manifest in the given game dir
+    pub fn get_path(game_dir: &Path) -> PathBuf {
+        return game_dir.join("game").join(MANIFEST_FILE_NAME);
+    }
+
+    /// Reads the manifest from the given game dir, returns None if there's none
+    pub fn read(game_dir: &Path) -> Result<Option<Self>, InstallError> {
+        let path = Self::get_path(game_dir);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let data = fs::read(path)?;
+
+        return Ok(Some(serde_json::from_slice(&data)?));
+    }
+
+    /// Records the files we've extracted from an archive, replaces the component if it's already there
+    pub fn add_component(&mut self, name: &str, archive: &str, files: Vec<InstalledFile>) {
+        self.components.retain(|component| component.name != name);
+        self.components.push(
+            InstalledComponent { name: name.to_string(), archive: archive.to_string(), files }
+        );
+    }
+
+    /// Returns the component with the given name
+    pub fn get_component(&self, name: &str) -> Option<&InstalledComponent> {
+        return self.components.iter().find(|component| component.name == name);
+    }
+
+    /// Writes the manifest of the given game dir into the given path, keeping the components
+    /// from the previous one that haven't been reinstalled and its backups, the file is replaced atomically
+    pub fn write(&mut self, game_dir: &Path, path: &Path) -> Result<(), InstallError> {
+        let previous = super::read_previous_manifest(game_dir);
+        if let Some(previous) = previous {
+            for component in previous.components {
+                if self.get_component(&component.name).is_none() {
+                    self.components.push(component);
+                }
+            }
+            let mut backups = previous.backups;
+            backups.append(&mut self.backups);
+            self.backups = backups;
+        }
+
+        self.installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let data = serde_json::to_vec_pretty(self)?;
+
+        return Ok(utils::write_file_atomically(path, &data)?);
+    }
+}
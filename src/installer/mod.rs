@@ -0,0 +1,798 @@
+/// The module that implements installer logic for IO handling
+
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+    fs::{self, File, create_dir_all, read_dir},
+    io::{self, Read},
+    thread,
+    time::Duration
+};
+
+use ::reqwest::blocking as reqwest;
+
+use zip::ZipArchive;
+
+use crate::{
+    errors::{
+        InstallError,
+        ExtractionError,
+        SignatureError
+    },
+    settings::{Settings, SymlinkPolicy}
+};
+
+
+pub mod backup;
+pub mod cache;
+pub mod checksum;
+pub mod download;
+pub mod dry_run;
+pub mod limits;
+pub mod manifest;
+pub mod network;
+pub mod permissions;
+pub mod preflight;
+pub mod release;
+pub mod reporter;
+pub mod retry;
+pub mod signature;
+pub mod symlinks;
+pub mod timestamps;
+pub mod transaction;
+pub mod uninstall;
+pub mod update;
+
+
+use backup::Backup;
+use cache::ReleaseCache;
+use limits::ExtractionLimits;
+use checksum::{Checksums, HashingWriter};
+use download::PartialDownload;
+use manifest::{InstallManifest, InstalledFile};
+use release::{GHAsset, LocalArchives, ReleaseData, ReleaseSelector};
+use reporter::{InstallEvent, Reporter};
+use transaction::Transaction;
+
+
+const PAUSE_DURATION: Duration = Duration::from_millis(200);
+
+
+pub type InstallResult = Result<(), InstallError>;
+pub type ContentSize = u64;
+
+/// Options for an installation
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// The DDLC directory to install into
+    pub extraction_dir: PathBuf,
+    /// Whether or not to install spritepacks
+    pub install_spr: bool,
+    /// Which release to install
+    pub release: ReleaseSelector,
+    /// If set, these archives get installed instead of downloading the release
+    pub local_archives: Option<LocalArchives>,
+    /// Whether or not to install a release without signed checksums
+    pub allow_unsigned: bool,
+    /// Where to get the release from, etc
+    pub settings: Settings
+}
+
+impl InstallOptions {
+    /// Creates new install options
+    pub fn new(
+        extraction_dir: PathBuf,
+        install_spr: bool,
+        release: ReleaseSelector,
+        settings: Settings
+    ) -> Self {
+        return Self {
+            extraction_dir,
+            install_spr,
+            release,
+            local_archives: None,
+            allow_unsigned: false,
+            settings
+        };
+    }
+}
+
+
+/// Blocks the thread for PAUSE_DURATION seconds
+fn sleep() {
+    thread::sleep(PAUSE_DURATION);
+}
+
+
+/// Lists the releases available for installing, newest first
+pub fn get_available_releases(settings: &Settings) -> Result<Vec<release::ReleaseData>, InstallError> {
+    settings.validate()?;
+    let client = network::build_client(settings)?;
+
+    return release::list_releases(&client, settings);
+}
+
+
+/// A file or dir we remove before installing
+struct StaleItem {
+    /// Path relative to the game dir, with '/' separators
+    rel_path: String,
+    is_dir: bool
+}
+
+/// Finds what should be removed from a DDLC directory before installing (some files/folders at the given path)
+/// rel_dir is the path of the directory relative to the game dir
+/// This function is "best-effort" and will ignore errors
+/// TPG, I know you will love these nested ifs
+fn find_stale_items(path: &Path, rel_dir: &str, remove_rpy: bool, remove_bin: bool) -> Vec<StaleItem> {
+    let mut stale_items = Vec::new();
+    if !path.is_dir() {
+        return stale_items;
+    }
+
+    let content = read_dir(path);
+    if content.is_err() {
+        return stale_items;
+    }
+
+    let content = content.unwrap();
+    for item in content {
+        if let Ok(item) = item {
+            let item_path = item.path();
+            let item_name = item.file_name();
+            let item_name = match item_name.to_str() {
+                Some(name) => name,
+                None => continue
+            };
+            let rel_path = match rel_dir.is_empty() {
+                true => item_name.to_string(),
+                false => format!("{rel_dir}/{item_name}")
+            };
+            // handle rpy files
+            if remove_rpy && item_path.is_file() {
+                let ext = item_path.extension();
+                if let Some(ext) = ext {
+                    let ext = ext.to_str();
+                    if ext.is_none() {
+                        continue;
+                    }
+                    let ext = ext.unwrap();
+                    match ext {
+                        "rpy" | "rpyc" => {
+                            stale_items.push(StaleItem { rel_path, is_dir: false });
+                        },
+                        _ => {}
+                    }
+                }
+            }
+            // handle folders with binaries
+            else if remove_bin && item_path.is_dir() {
+                let dir_name = item_path.file_name();
+                if let Some(dir_name) = dir_name {
+                    let dir_name = dir_name.to_str();
+                    if dir_name.is_none() {
+                        continue;
+                    }
+                    let dir_name = dir_name.unwrap();
+                    match dir_name {
+                        "renpy" | "lib" => {
+                            stale_items.push(StaleItem { rel_path, is_dir: true });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    return stale_items;
+}
+
+/// Downloads the release checksums and verifies their signature with our public key
+/// A release with an invalid signature is always refused, an unsigned one only if it's not explicitly allowed
+fn get_verified_checksums(
+    client: &reqwest::Client,
+    settings: &Settings,
+    data: &ReleaseData,
+    allow_unsigned: bool
+) -> Result<Checksums, InstallError> {
+    let checksums_asset = match data.checksums_asset {
+        Some(ref checksums_asset) => checksums_asset,
+        None if allow_unsigned => {
+            warn_unsigned(SignatureError::MissingChecksums);
+            return Ok(Checksums::missing());
+        },
+        None => return Err(SignatureError::MissingChecksums.into())
+    };
+    let checksums = checksum::request_text_asset(client, checksums_asset)?;
+
+    match data.signature_asset {
+        Some(ref signature_asset) => {
+            let signature = checksum::request_text_asset(client, signature_asset)?;
+            signature::verify(&settings.public_key, checksums.as_bytes(), &signature)?;
+        },
+        None if allow_unsigned => warn_unsigned(SignatureError::MissingSignature),
+        None => return Err(SignatureError::MissingSignature.into())
+    };
+
+    return Ok(Checksums::parse(&checksums));
+}
+
+/// Warns that we're installing an unsigned release the user has allowed
+fn warn_unsigned(error: SignatureError) {
+    eprintln!("Warning: installing an unverified release ({error}), it may have been tampered with");
+}
+
+
+/// A release archive we've got for the install
+enum AssetArchive {
+    /// Downloaded by this install, it's deleted or cached afterwards
+    Downloaded(PartialDownload),
+    /// Taken from the cache, it stays there
+    Cached(File)
+}
+
+impl AssetArchive {
+    /// Returns the file with the archive
+    fn get_file(&self) -> &File {
+        return match self {
+            Self::Downloaded(download) => download.get_file(),
+            Self::Cached(file) => file
+        };
+    }
+}
+
+/// Returns the archive of the given asset, it's taken from the cache if possible, otherwise downloaded
+fn get_asset_archive(
+    client: &reqwest::Client,
+    reporter: &dyn Reporter,
+    options: &InstallOptions,
+    cache: Option<&ReleaseCache>,
+    asset: &GHAsset,
+    checksums: &Checksums
+) -> Result<AssetArchive, InstallError> {
+    if let Some(cache) = cache {
+        if let Some(file) = cache.get(asset, checksums)? {
+            eprintln!("Using the cached '{}'", asset.name);
+            reporter.report(InstallEvent::Progress(1.0));
+            return Ok(AssetArchive::Cached(file));
+        }
+    }
+
+    // Partial downloads are kept here, so we can resume them if we get interrupted
+    let download = download::download_asset(
+        client,
+        reporter,
+        &download::get_downloads_dir(),
+        asset,
+        checksums,
+        options.settings.download_workers
+    )?;
+
+    return Ok(AssetArchive::Downloaded(download));
+}
+
+
+/// Returns the path the archive entry with the given name gets extracted to, relative to the destination
+/// The outer dir of the archive is skipped, so it returns None for the outer dir itself
+fn get_entry_path(name: &str) -> Result<Option<PathBuf>, ExtractionError> {
+    // Like ZipFile::enclosed_name, except we don't allow going up at all
+    let is_safe = !name.contains('\0') && Path::new(name).components().all(
+        |component| matches!(component, Component::Normal(_) | Component::CurDir)
+    );
+    if !is_safe {
+        return Err(ExtractionError::UnsafeFilepath(name.to_string()));
+    }
+
+    let file_path = Path::new(name).components()
+        .filter(|component| *component != Component::CurDir)
+        .skip(1)
+        .collect::<PathBuf>();
+    // Sanity check
+    if file_path.as_os_str().is_empty() {
+        return Ok(None);
+    }
+
+    return Ok(Some(file_path));
+}
+
+/// Converts a path relative to the dir of a component to its manifest form ('/' separators)
+fn to_manifest_path(path: &Path) -> String {
+    return path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+}
+
+/// Extracts a zip archive, returns the files we've extracted
+/// The archive is checked against the limits from the settings first, nothing is extracted if it's over them
+/// Symlinks in the archive are handled according to the symlink policy from the settings
+fn extract_archive(
+    reporter: &dyn Reporter,
+    archive: &File,
+    destination: &Path,
+    settings: &Settings
+) -> Result<Vec<InstalledFile>, ExtractionError> {
+    reporter.report(InstallEvent::Progress(0.0));
+
+    let mut installed_files = Vec::new();
+    if reporter.is_aborted() {
+        return Ok(installed_files);
+    }
+
+    let mut archive = ZipArchive::new(archive)?;
+    ExtractionLimits::new(settings).check_archive(&mut archive)?;
+    let total_files = archive.len();
+    create_dir_all(destination)?;
+    let canonical_destination = fs::canonicalize(destination)?;
+    // Extracting files into a dir changes its mtime, so the dirs are done at the end
+    let mut dir_mtimes = Vec::new();
+
+    for i in 0..total_files {
+        let mut file = archive.by_index(i)?;
+
+        let file_path = match get_entry_path(file.name())? {
+            Some(path) => path,
+            None => continue
+        };
+
+        let extraction_path = destination.join(&file_path);
+        let mtime = match settings.preserve_timestamps {
+            true => timestamps::get_entry_mtime(&file),
+            false => None
+        };
+
+        let is_symlink = symlinks::is_symlink(file.unix_mode());
+        if is_symlink && settings.symlink_policy == SymlinkPolicy::Refuse {
+            return Err(ExtractionError::SymlinkEntry(file.name().to_string()));
+        }
+
+        // The symlinks on the way must lead inside the destination, create_dir_all would follow them
+        symlinks::verify_inside(&canonical_destination, &extraction_path)?;
+
+        // Extract the dir
+        if file.is_dir() {
+            create_dir_all(&extraction_path)?;
+            if let Some(mtime) = mtime {
+                dir_mtimes.push((extraction_path, mtime));
+            }
+            continue;
+        }
+
+        // Create the parent dir if needed
+        if let Some(parent_dir) = extraction_path.parent() {
+            if !parent_dir.exists() {
+                create_dir_all(parent_dir)?;
+            }
+        }
+        let manifest_path = to_manifest_path(&file_path);
+
+        // Extract the symlink, with the replace policy it's extracted as a file with its target
+        if is_symlink && settings.symlink_policy == SymlinkPolicy::Follow {
+            installed_files.push(
+                symlinks::extract_symlink(&mut file, &canonical_destination, &file_path, manifest_path)?
+            );
+        }
+        // Extract the file
+        else {
+            // Create the file and write to it, hashing it for the manifest
+            let mut outfile = HashingWriter::new(File::create(&extraction_path)?);
+            // The size has been checked against the limits, but the data can be longer than it says
+            let expected_size = file.size();
+            let size = io::copy(&mut (&mut file).take(expected_size + 1), &mut outfile)?;
+            if size > expected_size {
+                return Err(ExtractionError::SizeMismatch(file.name().to_string()));
+            }
+            // The launchers wouldn't run on linux otherwise, the mode of a symlink is meaningless for a file
+            let mode = file.unix_mode().filter(|_| !is_symlink);
+            permissions::apply(&extraction_path, &manifest_path, mode)?;
+            installed_files.push(
+                InstalledFile { path: manifest_path, size, sha256: outfile.finalize() }
+            );
+            // The file is closed by now, so nothing changes the mtime after this
+            if let Some(mtime) = mtime {
+                timestamps::set_mtime(&extraction_path, mtime);
+            }
+        }
+
+        // Update progres bar
+        let pb_val = (i as f64 + 1.0) / total_files as f64;
+        reporter.report(InstallEvent::Progress(pb_val));
+
+        // See if we want to abort
+        if reporter.is_aborted() {
+            return Ok(installed_files);
+        }
+    }
+    for (path, mtime) in dir_mtimes {
+        timestamps::set_mtime(&path, mtime);
+    }
+
+    return Ok(installed_files);
+}
+
+
+/// This runs cleanup logic on SUCCESSFUL install
+/// Downloaded archives are moved into the cache if it's enabled, otherwise deleted,
+/// so are the finished downloads earlier installs have left behind
+fn cleanup(reporter: &dyn Reporter, cache: Option<&ReleaseCache>, archives: Vec<(&GHAsset, AssetArchive)>) {
+    reporter.report(InstallEvent::CleaningUp);
+    reporter.report(InstallEvent::Progress(0.0));
+    for (asset, archive) in archives {
+        if let AssetArchive::Downloaded(download) = archive {
+            match cache {
+                Some(cache) => cache.store(asset, download),
+                None => download.remove()
+            };
+        }
+    }
+    download::remove_finished_downloads(&download::get_downloads_dir());
+    sleep();
+    reporter.report(InstallEvent::Progress(1.0));
+    sleep();
+    reporter.report(InstallEvent::Done);
+}
+
+
+/// Returns the file name of the given path as a string
+fn get_file_name(path: &Path) -> String {
+    return path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+}
+
+/// Returns the dir the game gets extracted into
+fn get_game_dir(extraction_dir: &Path) -> PathBuf {
+    let mut path = extraction_dir.to_path_buf();
+    // Since mac is pain, we have to adjust the destination to be
+    // within the app
+    if env::consts::OS == "macos" {
+        path.push("Contents/Resources/autorun");
+    }
+    return path;
+}
+
+/// Returns the dir spritepacks get extracted into
+fn get_spritepacks_dir(extraction_dir: &Path) -> PathBuf {
+    let mut path = extraction_dir.to_path_buf();
+    // We don't want to add spritepacks inside the app, so use the parent dir
+    if env::consts::OS == "macos" {
+        path.pop();
+    }
+    return path.join("spritepacks");
+}
+
+/// Returns the dir the given component gets extracted into
+fn get_component_dir(extraction_dir: &Path, component: &str) -> Option<PathBuf> {
+    return match component {
+        manifest::COMPONENT_GAME => Some(get_game_dir(extraction_dir)),
+        manifest::COMPONENT_SPRITEPACKS => Some(get_spritepacks_dir(extraction_dir)),
+        _ => None
+    };
+}
+
+/// Joins a path from the manifest ('/' separators) to the given root
+/// Returns None if the path could lead outside of the root
+fn join_relative_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut rv = root.to_path_buf();
+    for part in path.split('/') {
+        match Path::new(part).components().next() {
+            Some(Component::Normal(_)) if !part.contains('\\') => rv.push(part),
+            _ => return None
+        };
+    }
+    return Some(rv);
+}
+
+/// Moves a file, creating the parent dirs of the target if needed
+/// Falls back to copying if the file can't be renamed
+fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent_dir) = target.parent() {
+        create_dir_all(parent_dir)?;
+    }
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, target)?;
+
+    return fs::remove_file(source);
+}
+
+/// Deletes the dirs between the given dir and the root while they're empty, the root is kept
+fn remove_empty_dirs(dir: Option<&Path>, root: &Path) {
+    let mut dir = dir;
+    while let Some(path) = dir {
+        if path == root || !path.starts_with(root) || fs::remove_dir(path).is_err() {
+            return;
+        }
+        dir = path.parent();
+    }
+}
+
+/// Removes old rpy/rpyc from the game dir, they're moved into the backup
+/// This function is "best-effort" and will ignore errors
+fn prepare_game_dir(destination: &Path, backup: &mut Backup) {
+    for item in find_stale_game_items(destination) {
+        let rv = match item.is_dir {
+            true => backup.remove_dir_all(manifest::COMPONENT_GAME, &item.rel_path),
+            false => backup.remove_file(manifest::COMPONENT_GAME, &item.rel_path)
+        };
+        if let Err(e) = rv {
+            eprintln!("Failed to delete '{}': {e}", destination.join(&item.rel_path).display());
+        }
+    }
+}
+
+/// Finds the old rpy/rpyc prepare_game_dir removes from the game dir
+fn find_stale_game_items(destination: &Path) -> Vec<StaleItem> {
+    // Yeah...some people have rpy in the base dir...
+    let mut stale_items = find_stale_items(destination, "", true, false);
+    stale_items.extend(find_stale_items(&destination.join("game"), "game", true, false));
+
+    return stale_items;
+}
+
+
+/// Reads the manifest of the previous install, it's fine if it's broken
+fn read_previous_manifest(destination: &Path) -> Option<InstallManifest> {
+    return InstallManifest::read(destination).unwrap_or_else(
+        |e| {
+            eprintln!("Ignoring the previous install manifest: {e}");
+            None
+        }
+    );
+}
+
+
+/// Main method to handle game installation process, downloads it into the cache folder and then extracts
+/// Progress is reported to the given reporter, which is also polled for abort requests
+/// Everything is extracted into a staging dir first, the game dir is only changed
+/// once that has succeeded, and it's rolled back if anything fails or the install is aborted
+pub fn install_game(
+    options: &InstallOptions,
+    reporter: &dyn Reporter
+) -> InstallResult {
+    reporter.report(InstallEvent::Preparing);
+    reporter.report(InstallEvent::Progress(0.0));
+
+    if reporter.is_aborted() {
+        return Ok(());
+    }
+
+    return match options.local_archives {
+        Some(ref archives) => install_from_archives(options, archives, reporter),
+        None => install_from_release(options, reporter)
+    };
+}
+
+/// Downloads the selected release and installs it
+fn install_from_release(
+    options: &InstallOptions,
+    reporter: &dyn Reporter
+) -> InstallResult {
+    options.settings.validate()?;
+    let client = network::build_client(&options.settings)?;
+
+    // Get download link
+    let data = release::get_release_data(&client, &options.settings, &options.release)?;
+    // Refuse to touch anything if we can't trust the release
+    let checksums = get_verified_checksums(&client, &options.settings, &data, options.allow_unsigned)?;
+    let cache = ReleaseCache::open(&options.settings, &data.version, &checksums);
+    let mut manifest = InstallManifest::new(Some(data.version.clone()), Some(data.name.clone()));
+    // Find out if we have the space and the permissions before anything is downloaded or changed
+    preflight::check_release(&client, options, cache.as_ref(), &data, &checksums)?;
+    if reporter.is_aborted() {
+        return Ok(());
+    }
+
+    let transaction = Transaction::begin(&options.extraction_dir, &options.settings)?;
+    reporter.report(InstallEvent::Progress(1.0));
+    sleep();
+
+    let rv = stage_release(
+        &client,
+        options,
+        reporter,
+        cache.as_ref(),
+        &data,
+        &checksums,
+        &transaction,
+        &mut manifest
+    );
+    let archives = match rv {
+        Ok(Some(archives)) => archives,
+        Ok(None) => {
+            transaction.discard();
+            return Ok(());
+        },
+        Err(e) => {
+            transaction.discard();
+            return Err(e);
+        }
+    };
+
+    if !transaction.commit(reporter, manifest)? {
+        return Ok(());
+    }
+    cleanup(reporter, cache.as_ref(), archives);
+
+    return Ok(());
+}
+
+/// Downloads the release assets and extracts them into the staging dir
+/// Returns the archives to clean up afterwards, or None if the install has been aborted
+#[allow(clippy::too_many_arguments)]
+fn stage_release<'a>(
+    client: &reqwest::Client,
+    options: &InstallOptions,
+    reporter: &dyn Reporter,
+    cache: Option<&ReleaseCache>,
+    data: &'a ReleaseData,
+    checksums: &Checksums,
+    transaction: &Transaction,
+    manifest: &mut InstallManifest
+) -> Result<Option<Vec<(&'a GHAsset, AssetArchive)>>, InstallError> {
+    let main_asset = &data.def_ver_asset;
+    // let main_asset = match options.is_dlx_version {
+    //     true => &data.dlx_ver_asset,
+    //     false => &data.def_ver_asset
+    // };
+
+    // Install MAS
+    reporter.report(InstallEvent::Downloading);
+    let mas_archive = get_asset_archive(
+        client,
+        reporter,
+        options,
+        cache,
+        main_asset,
+        checksums
+    )?;
+    if reporter.is_aborted() {
+        return Ok(None);
+    }
+    sleep();
+
+    reporter.report(InstallEvent::Extracting);
+    let files = extract_archive(
+        reporter,
+        mas_archive.get_file(),
+        &transaction.get_staging_dir(manifest::COMPONENT_GAME),
+        &options.settings
+    )?;
+    if reporter.is_aborted() {
+        return Ok(None);
+    }
+    manifest.add_component(manifest::COMPONENT_GAME, &main_asset.name, files);
+    sleep();
+
+    let mut archives = vec![(main_asset, mas_archive)];
+    // Quit early if the user doesn't want spritepacks
+    if !options.install_spr {
+        return Ok(Some(archives));
+    }
+
+    // Install spritepacks
+    reporter.report(InstallEvent::DownloadingSpr);
+    let spr_archive = get_asset_archive(
+        client,
+        reporter,
+        options,
+        cache,
+        &data.spr_asset,
+        checksums
+    )?;
+    if reporter.is_aborted() {
+        return Ok(None);
+    }
+    sleep();
+
+    reporter.report(InstallEvent::ExtractingSpr);
+    let files = extract_archive(
+        reporter,
+        spr_archive.get_file(),
+        &transaction.get_staging_dir(manifest::COMPONENT_SPRITEPACKS),
+        &options.settings
+    )?;
+    if reporter.is_aborted() {
+        return Ok(None);
+    }
+    manifest.add_component(manifest::COMPONENT_SPRITEPACKS, &data.spr_asset.name, files);
+    archives.push((&data.spr_asset, spr_archive));
+    sleep();
+
+    return Ok(Some(archives));
+}
+
+/// Installs the game from archives the user downloaded by hand, this doesn't need network access
+fn install_from_archives(
+    options: &InstallOptions,
+    archives: &LocalArchives,
+    reporter: &dyn Reporter
+) -> InstallResult {
+    // Open everything first so we fail before touching the game dir
+    let game_file = File::open(&archives.game)?;
+    let spr_file = match archives.spritepacks {
+        Some(ref path) if options.install_spr => Some(File::open(path)?),
+        None if options.install_spr => return Err(InstallError::MissingArchive("spritepacks")),
+        _ => None
+    };
+    preflight::check_archives(options, &game_file, spr_file.as_ref())?;
+    // We don't know the release, but the archives names have the version
+    let mut manifest = InstallManifest::new(None, None);
+
+    let transaction = Transaction::begin(&options.extraction_dir, &options.settings)?;
+    reporter.report(InstallEvent::Progress(1.0));
+    sleep();
+
+    let rv = stage_archives(
+        reporter,
+        options,
+        archives,
+        &game_file,
+        spr_file.as_ref(),
+        &transaction,
+        &mut manifest
+    );
+    match rv {
+        Ok(true) => {},
+        Ok(false) => {
+            transaction.discard();
+            return Ok(());
+        },
+        Err(e) => {
+            transaction.discard();
+            return Err(e);
+        }
+    };
+
+    if !transaction.commit(reporter, manifest)? {
+        return Ok(());
+    }
+    // The archives are the user's, we don't delete them
+    cleanup(reporter, None, Vec::new());
+
+    return Ok(());
+}
+
+/// Extracts the local archives into the staging dir, returns false if the install has been aborted
+fn stage_archives(
+    reporter: &dyn Reporter,
+    options: &InstallOptions,
+    archives: &LocalArchives,
+    game_file: &File,
+    spr_file: Option<&File>,
+    transaction: &Transaction,
+    manifest: &mut InstallManifest
+) -> Result<bool, InstallError> {
+    reporter.report(InstallEvent::Extracting);
+    let files = extract_archive(
+        reporter,
+        game_file,
+        &transaction.get_staging_dir(manifest::COMPONENT_GAME),
+        &options.settings
+    )?;
+    if reporter.is_aborted() {
+        return Ok(false);
+    }
+    manifest.add_component(manifest::COMPONENT_GAME, &get_file_name(&archives.game), files);
+    sleep();
+
+    if let Some(spr_file) = spr_file {
+        reporter.report(InstallEvent::ExtractingSpr);
+        let files = extract_archive(
+            reporter,
+            spr_file,
+            &transaction.get_staging_dir(manifest::COMPONENT_SPRITEPACKS),
+            &options.settings
+        )?;
+        if reporter.is_aborted() {
+            return Ok(false);
+        }
+        if let Some(ref path) = archives.spritepacks {
+            manifest.add_component(manifest::COMPONENT_SPRITEPACKS, &get_file_name(path), files);
+        }
+        sleep();
+    }
+
+    return Ok(true);
+}
@@ -0,0 +1,134 @@
+/// The module that implements building the HTTP client: proxy, timeouts and extra trust roots
+
+use std::{
+    env,
+    fs,
+    io,
+    net::IpAddr,
+    path::Path,
+    time::Duration
+};
+
+use ::reqwest::{
+    blocking as reqwest,
+    Certificate,
+    Proxy
+};
+
+use crate::{
+    errors::{InstallError, SettingsError},
+    settings::Settings
+};
+
+
+const PEM_CERT_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_CERT_END: &str = "-----END CERTIFICATE-----";
+
+
+/// Hosts that bypass the proxy, in the same format as NO_PROXY
+/// Entries are hosts or IPs, a host entry also matches its subdomains, "*" matches everything
+struct NoProxy {
+    entries: Vec<String>
+}
+
+impl NoProxy {
+    /// Parses a comma-separated list of hosts
+    fn parse(value: &str) -> Self {
+        let entries = value.split(',')
+            .map(|entry| entry.trim().trim_start_matches('.').to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+
+        return Self { entries };
+    }
+
+    /// Checks if the given host bypasses the proxy
+    fn contains(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+        // Only domains have subdomains
+        let is_domain = host.parse::<IpAddr>().is_err();
+
+        return self.entries.iter().any(
+            |entry| {
+                entry == "*"
+                || *entry == host
+                || (is_domain && host.ends_with(&format!(".{entry}")))
+            }
+        );
+    }
+}
+
+
+/// Builds the proxy from the settings, returns None if we should use the system proxy
+fn build_proxy(settings: &Settings) -> Result<Option<Proxy>, SettingsError> {
+    let proxy_url = match settings.proxy {
+        Some(ref url) => url.clone(),
+        None => return Ok(None)
+    };
+    // Check the URL now, the custom proxy would just ignore it
+    Proxy::all(&proxy_url).map_err(|_| SettingsError::InvalidValue("proxy"))?;
+
+    let no_proxy = settings.no_proxy.clone()
+        .or_else(|| env::var("NO_PROXY").ok())
+        .or_else(|| env::var("no_proxy").ok())
+        .unwrap_or_default();
+    let no_proxy = NoProxy::parse(&no_proxy);
+
+    let proxy = Proxy::custom(
+        move |url| {
+            if url.host_str().map_or(false, |host| no_proxy.contains(host)) {
+                return None;
+            }
+            return Some(proxy_url.clone());
+        }
+    );
+
+    return Ok(Some(proxy));
+}
+
+/// Reads all certificates from a PEM file
+fn read_ca_bundle(path: &Path) -> Result<Vec<Certificate>, InstallError> {
+    let data = fs::read_to_string(path).map_err(
+        |e| io::Error::new(e.kind(), format!("couldn't read the CA bundle '{}': {e}", path.display()))
+    )?;
+    let mut certs = Vec::new();
+    let mut rest = data.as_str();
+
+    while let Some(start) = rest.find(PEM_CERT_BEGIN) {
+        let end = rest[start..].find(PEM_CERT_END)
+            .ok_or(SettingsError::InvalidValue("ca_bundle"))?;
+        let end = start + end + PEM_CERT_END.len();
+
+        let cert = Certificate::from_pem(rest[start..end].as_bytes())
+            .map_err(|_| SettingsError::InvalidValue("ca_bundle"))?;
+        certs.push(cert);
+        rest = &rest[end..];
+    }
+
+    if certs.is_empty() {
+        return Err(SettingsError::InvalidValue("ca_bundle").into());
+    }
+
+    return Ok(certs);
+}
+
+/// Builds a client for this installer to access GitHub API and download the assets
+/// The read timeout applies to each read, so it also catches stalled downloads
+pub fn build_client(settings: &Settings) -> Result<reqwest::Client, InstallError> {
+    let headers = crate::HEADERS.clone();
+    let mut builder = reqwest::Client::builder()
+        .default_headers(headers)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout))
+        .timeout(Duration::from_secs(settings.read_timeout));
+
+    if let Some(proxy) = build_proxy(settings)? {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(ref path) = settings.ca_bundle {
+        for cert in read_ca_bundle(path)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    return Ok(builder.build()?);
+}
@@ -0,0 +1,178 @@
+/// The module that restores the Unix permissions of extracted files
+/// Zip archives keep the mode of every file, but we create the files ourselves, so it'd be lost otherwise
+/// On other systems there's nothing to restore
+
+use std::{io, path::Path};
+
+
+/// Launchers that must be executable, relative to the game dir
+const LAUNCHER_SCRIPTS: [&str; 2] = ["DDLC.sh", "renpy.sh"];
+/// The dir with the Ren'Py binaries for linux is 'lib/linux-<arch>'
+const LINUX_LIB_DIR_PREFIX: &str = "linux-";
+
+// We don't keep setuid/setgid/sticky bits from an archive
+const PERMISSION_BITS: u32 = 0o777;
+// We should be able to update/remove what we've installed
+const OWNER_RW_BITS: u32 = 0o600;
+
+
+/// Returns whether the file at the given manifest path must be executable,
+/// that's the launcher scripts and the binaries in lib/linux-*
+fn needs_exec_bit(rel_path: &str) -> bool {
+    let parts: Vec<&str> = rel_path.split('/').collect();
+    return match parts.as_slice() {
+        [name] => LAUNCHER_SCRIPTS.contains(name),
+        ["lib", dir, _] => dir.starts_with(LINUX_LIB_DIR_PREFIX),
+        _ => false
+    };
+}
+
+/// Sets the permissions of an extracted file, mode is the one from the archive entry (if it has one)
+/// Everyone who can read a file that needs the exec bit can also execute it
+#[cfg(unix)]
+pub fn apply(path: &Path, rel_path: &str, mode: Option<u32>) -> io::Result<()> {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    // Archives made on unix without the modes have them all empty
+    let mode = mode.filter(|mode| mode & PERMISSION_BITS != 0);
+    let needs_exec_bit = needs_exec_bit(rel_path);
+    if mode.is_none() && !needs_exec_bit {
+        return Ok(());
+    }
+
+    let mut mode = match mode {
+        Some(mode) => (mode & PERMISSION_BITS) | OWNER_RW_BITS,
+        None => fs::metadata(path)?.permissions().mode() & PERMISSION_BITS
+    };
+    if needs_exec_bit {
+        mode |= (mode & 0o444) >> 2;
+    }
+
+    return fs::set_permissions(path, fs::Permissions::from_mode(mode));
+}
+
+/// Sets the permissions of an extracted file, there's nothing to set on this system
+#[cfg(not(unix))]
+pub fn apply(_path: &Path, _rel_path: &str, _mode: Option<u32>) -> io::Result<()> {
+    return Ok(());
+}
+
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io::Write,
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        process
+    };
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::needs_exec_bit;
+    use crate::{
+        installer::{extract_archive, reporter::{InstallEvent, Reporter}},
+        settings::Settings
+    };
+
+
+    struct NullReporter;
+
+    impl Reporter for NullReporter {
+        fn report(&self, _event: InstallEvent) {}
+    }
+
+    /// Returns an empty dir for a test
+    fn get_test_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("jn-installer-test-{}-{name}", process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+
+        return path;
+    }
+
+    /// Writes an archive with the given files and modes, they're put in an outer dir like in the releases
+    fn write_archive(path: &Path, files: &[(&str, Option<u32>)]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, mode) in files {
+            let mut options = FileOptions::default();
+            if let Some(mode) = mode {
+                options = options.unix_permissions(*mode);
+            }
+            writer.start_file(format!("JN/{name}"), options).unwrap();
+            writer.write_all(b"#!/bin/sh\n").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn get_mode(path: &Path) -> u32 {
+        return fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+    }
+
+    #[test]
+    fn test_needs_exec_bit() {
+        assert!(needs_exec_bit("DDLC.sh"));
+        assert!(needs_exec_bit("renpy.sh"));
+        assert!(needs_exec_bit("lib/linux-x86_64/DDLC"));
+        assert!(needs_exec_bit("lib/linux-i686/pythonw"));
+
+        assert!(!needs_exec_bit("game/DDLC.sh"));
+        assert!(!needs_exec_bit("lib/windows-i686/DDLC.exe"));
+        assert!(!needs_exec_bit("lib/linux-x86_64/lib/python2.7/os.py"));
+        assert!(!needs_exec_bit("game/script.rpy"));
+    }
+
+    #[test]
+    fn test_extract_keeps_modes() {
+        let dir = get_test_dir("modes");
+        let archive_path = dir.join("archive.zip");
+        write_archive(
+            &archive_path,
+            &[
+                ("game/tool.py", Some(0o755)),
+                ("game/script.rpy", Some(0o640)),
+                ("game/readonly.txt", Some(0o444)),
+                ("game/setuid", Some(0o4755))
+            ]
+        );
+
+        let destination = dir.join("out");
+        extract_archive(&NullReporter, &File::open(&archive_path).unwrap(), &destination, &Settings::default()).unwrap();
+
+        assert_eq!(get_mode(&destination.join("game/tool.py")), 0o755);
+        assert_eq!(get_mode(&destination.join("game/script.rpy")), 0o640);
+        assert_eq!(get_mode(&destination.join("game/readonly.txt")), 0o644);
+        assert_eq!(get_mode(&destination.join("game/setuid")), 0o755);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_sets_exec_bit() {
+        let dir = get_test_dir("exec");
+        let archive_path = dir.join("archive.zip");
+        write_archive(
+            &archive_path,
+            &[
+                ("DDLC.sh", Some(0o644)),
+                ("lib/linux-x86_64/DDLC", Some(0o640)),
+                ("lib/linux-x86_64/lib/libpython2.7.so", Some(0o644)),
+                ("game/DDLC.sh", Some(0o644))
+            ]
+        );
+
+        let destination = dir.join("out");
+        extract_archive(&NullReporter, &File::open(&archive_path).unwrap(), &destination, &Settings::default()).unwrap();
+
+        assert_eq!(get_mode(&destination.join("DDLC.sh")), 0o755);
+        assert_eq!(get_mode(&destination.join("lib/linux-x86_64/DDLC")), 0o750);
+        assert_eq!(get_mode(&destination.join("lib/linux-x86_64/lib/libpython2.7.so")), 0o644);
+        assert_eq!(get_mode(&destination.join("game/DDLC.sh")), 0o644);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+/// The module that checks an install can go ahead before anything is downloaded or changed
+/// It makes sure there's enough free space for the downloads and the extracted files,
+/// and that we can write into the dirs the install touches
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf}
+};
+
+use ::reqwest::blocking as reqwest;
+
+use crate::errors::{InstallError, PreflightProblem};
+use super::{
+    cache::ReleaseCache,
+    checksum::Checksums,
+    download::{self, RemoteFile},
+    dry_run::get_extracted_size,
+    get_game_dir,
+    get_spritepacks_dir,
+    release::{GHAsset, ReleaseData},
+    ContentSize,
+    InstallOptions
+};
+
+
+/// Name of the file we create to find out if a dir is writable
+const PROBE_FILE_NAME: &str = ".jn_write_probe";
+
+
+/// How much space an install needs
+#[derive(Debug, Default)]
+struct Requirements {
+    /// What gets downloaded into the downloads dir
+    download_size: ContentSize,
+    /// What gets extracted into the game dir
+    write_size: ContentSize
+}
+
+
+/// Checks the release can be installed with the given options
+/// The archives are taken from the cache if possible, otherwise only their central directories are downloaded
+pub fn check_release(
+    client: &reqwest::Client,
+    options: &InstallOptions,
+    cache: Option<&ReleaseCache>,
+    data: &ReleaseData,
+    checksums: &Checksums
+) -> Result<(), InstallError> {
+    let mut assets = vec![&data.def_ver_asset];
+    if options.install_spr {
+        assets.push(&data.spr_asset);
+    }
+
+    let mut requirements = Requirements::default();
+    for asset in assets {
+        let cached_file = match cache {
+            Some(cache) => cache.get(asset, checksums)?,
+            None => None
+        };
+        requirements.write_size += match cached_file {
+            Some(mut file) => get_extracted_size(&mut file)?,
+            None => {
+                requirements.download_size += asset.size;
+                get_remote_extracted_size(client, asset)
+            }
+        };
+    }
+
+    return check_requirements(options, &requirements, Some(&download::get_downloads_dir()));
+}
+
+/// Checks the local archives can be installed with the given options
+pub fn check_archives(
+    options: &InstallOptions,
+    game_file: &File,
+    spr_file: Option<&File>
+) -> Result<(), InstallError> {
+    let mut requirements = Requirements::default();
+    for mut file in [Some(game_file), spr_file].into_iter().flatten() {
+        requirements.write_size += get_extracted_size(&mut file)?;
+    }
+
+    return check_requirements(options, &requirements, None);
+}
+
+/// Returns how much the files of a remote archive take once extracted
+/// If we can't read its central directory (e.g. a mirror doesn't support partial downloads),
+/// the size of the archive is used instead, it's the least the files can take
+fn get_remote_extracted_size(client: &reqwest::Client, asset: &GHAsset) -> ContentSize {
+    return match get_extracted_size(&mut RemoteFile::new(client, asset)) {
+        Ok(size) => size,
+        Err(e) => {
+            eprintln!("Couldn't read the file list of '{}', using its size: {e}", asset.name);
+            asset.size
+        }
+    };
+}
+
+/// Checks the dirs the install touches are writable and have enough free space,
+/// downloads_dir is None if nothing gets downloaded
+fn check_requirements(
+    options: &InstallOptions,
+    requirements: &Requirements,
+    downloads_dir: Option<&Path>
+) -> Result<(), InstallError> {
+    let mut problems = Vec::new();
+
+    // The dirs that don't exist yet get created in their parent dirs, so we check those instead
+    let game_dir = get_existing_dir(&get_game_dir(&options.extraction_dir));
+    let mut dirs = vec![get_existing_dir(&options.extraction_dir), game_dir.clone()];
+    if options.install_spr {
+        dirs.push(get_existing_dir(&get_spritepacks_dir(&options.extraction_dir)));
+    }
+    if let Some(downloads_dir) = downloads_dir {
+        // It's our own dir, so it's fine to create it
+        match fs::create_dir_all(downloads_dir) {
+            Ok(_) => dirs.push(downloads_dir.to_path_buf()),
+            Err(e) => problems.push(PreflightProblem::NotWritable(downloads_dir.to_path_buf(), e))
+        };
+    }
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        if let Err(e) = probe_write_access(&dir) {
+            problems.push(PreflightProblem::NotWritable(dir, e));
+        }
+    }
+
+    // The staging dir is in the game dir, everything else is only moved around
+    let mut spaces = vec![(game_dir, requirements.write_size)];
+    if let Some(downloads_dir) = downloads_dir {
+        if is_same_filesystem(downloads_dir, &spaces[0].0) {
+            spaces[0].1 += requirements.download_size;
+        }
+        else {
+            spaces.push((downloads_dir.to_path_buf(), requirements.download_size));
+        }
+    }
+    for (path, needed) in spaces {
+        match fs2::available_space(&path) {
+            Ok(available) if available < needed => {
+                problems.push(PreflightProblem::NotEnoughSpace { path, needed, available });
+            },
+            Ok(_) => {},
+            // The write check reports what's wrong with the dir
+            Err(e) => eprintln!("Couldn't get the free space of '{}': {e}", path.display())
+        };
+    }
+
+    if !problems.is_empty() {
+        return Err(InstallError::PreflightFailed(problems));
+    }
+    return Ok(());
+}
+
+/// Returns the given dir, or its closest parent that exists
+fn get_existing_dir(dir: &Path) -> PathBuf {
+    return dir.ancestors()
+        .find(|path| path.is_dir())
+        .unwrap_or(dir)
+        .to_path_buf();
+}
+
+/// Checks we can create files in the given dir, the probe file is deleted right away
+fn probe_write_access(dir: &Path) -> io::Result<()> {
+    let path = dir.join(PROBE_FILE_NAME);
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+
+    return fs::remove_file(&path);
+}
+
+/// Returns whether the given paths are on the same filesystem
+#[cfg(unix)]
+fn is_same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    return match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        // Assume the worst, then everything has to fit in one place
+        _ => true
+    };
+}
+
+/// Returns whether the given paths are on the same filesystem
+/// NOTE: this only compares the drives, which is good enough for the free space check
+#[cfg(not(unix))]
+fn is_same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::path::Component;
+
+    let get_prefix = |path: &Path| -> Option<PathBuf> {
+        let path = fs::canonicalize(path).ok()?;
+        return match path.components().next() {
+            Some(Component::Prefix(prefix)) => Some(PathBuf::from(prefix.as_os_str())),
+            _ => None
+        };
+    };
+
+    return match (get_prefix(a), get_prefix(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => true
+    };
+}
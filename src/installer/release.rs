@@ -0,0 +1,476 @@
+/// The module that implements fetching release data from GitHub
+
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use ::reqwest::{
+    blocking as reqwest,
+    header as headers,
+    StatusCode,
+    Url
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    errors::{DownloadError, InstallError, SettingsError},
+    settings::Settings
+};
+use super::{checksum, retry, ContentSize};
+
+
+// How many releases we request per page
+const RELEASES_PER_PAGE: usize = 100;
+// Sanity limit so a broken API can't keep us paginating forever
+const MAX_RELEASES_PAGES: usize = 10;
+
+// Headers GitHub uses to report the rate limit
+const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+
+
+/// Which release to install
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReleaseSelector {
+    /// The latest stable release
+    Latest,
+    /// The newest release, including pre-releases
+    LatestPrerelease,
+    /// The release with the given tag
+    Tag(String)
+}
+
+impl ReleaseSelector {
+    /// Returns a selector for the given channel name
+    pub fn from_channel(channel: &str) -> Option<Self> {
+        return match channel {
+            "stable" | "latest" => Some(Self::Latest),
+            "beta" | "prerelease" => Some(Self::LatestPrerelease),
+            _ => None
+        };
+    }
+}
+
+impl Default for ReleaseSelector {
+    fn default() -> Self {
+        return Self::Latest;
+    }
+}
+
+
+/// Struct representing release data we may need
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct ReleaseData {
+    pub version: String,
+    pub name: String,
+    pub is_prerelease: bool,
+    pub def_ver_asset: GHAsset,
+    // dlx_ver_asset: GHAsset,
+    pub spr_asset: GHAsset,
+    /// SHA-256 digests of the assets, older releases don't have them
+    pub checksums_asset: Option<GHAsset>,
+    /// Signature of the checksums
+    pub signature_asset: Option<GHAsset>,
+    /// Manifest with the mirrors of the assets
+    pub mirrors_asset: Option<GHAsset>
+}
+
+impl ReleaseData {
+    /// Creates new release data
+    pub fn new(
+        version: String,
+        name: String,
+        is_prerelease: bool,
+        def_ver_asset: GHAsset,
+        // dlx_ver_asset: GHAsset,
+        spr_asset: GHAsset,
+        checksums_asset: Option<GHAsset>,
+        signature_asset: Option<GHAsset>,
+        mirrors_asset: Option<GHAsset>
+    ) -> Self {
+        return Self {
+            version,
+            name,
+            is_prerelease,
+            def_ver_asset,
+            spr_asset,
+            checksums_asset,
+            signature_asset,
+            mirrors_asset
+        };
+    }
+
+    /// Builds release data from a GitHub release by finding the assets we need
+    fn from_release(release: GHRelease, settings: &Settings) -> Result<Self, InstallError> {
+        if !release.is_valid() {
+            eprintln!("Release '{:?}' is invalid", release);
+            return Err(InstallError::CorruptedJSON("Selected release is invalid"));
+        }
+
+        // Create a map of the assets we need
+        let mut assets_map = HashMap::new();
+        let mut checksums_asset = None;
+        let mut signature_asset = None;
+        let mut mirrors_asset = None;
+
+        // Search thru all the available assets and find the ones we need
+        'outer_loop: for asset in release.assets {
+            if checksums_asset.is_none() && crate::CHECKSUMS_ASSET_RE.is_match(&asset.name) {
+                if !asset.is_downloadable(settings) {
+                    eprintln!("Asset '{}' is invalid", asset.name);
+                    return Err(InstallError::CorruptedJSON("Found the checksums asset, but it's invalid"));
+                }
+                checksums_asset = Some(asset);
+                continue;
+            }
+            if signature_asset.is_none() && crate::SIGNATURE_ASSET_RE.is_match(&asset.name) {
+                if !asset.is_downloadable(settings) {
+                    eprintln!("Asset '{}' is invalid", asset.name);
+                    return Err(InstallError::CorruptedJSON("Found the signature asset, but it's invalid"));
+                }
+                signature_asset = Some(asset);
+                continue;
+            }
+            if mirrors_asset.is_none() && crate::MIRRORS_ASSET_RE.is_match(&asset.name) {
+                // The mirrors are optional, so a broken manifest isn't fatal
+                if asset.is_downloadable(settings) {
+                    mirrors_asset = Some(asset);
+                }
+                continue;
+            }
+            // Use regex to find the assets
+            for (k, v) in crate::ASSETS_NAMES_RE_MAP.iter() {
+                if !assets_map.contains_key(k) && v.is_match(&asset.name) {
+                    if !asset.is_valid(settings) {
+                        eprintln!("Asset '{}' is invalid", asset.name);
+                        return Err(InstallError::CorruptedJSON("Found a required asset, but it's invalid"));
+                    }
+                    assets_map.insert(*k, asset);
+                    // We need to move to the next asset since this once has been moved
+                    continue 'outer_loop;
+                }
+            }
+        }
+
+        if assets_map.len() != crate::ASSETS_NAMES_RE_MAP.len() {
+            return Err(InstallError::CorruptedJSON("An asset is missing from the release"));
+        }
+
+        let data = Self::new(
+            release.tag_name,
+            release.name,
+            release.prerelease,
+            assets_map.remove("def_ver").unwrap(),
+            // assets_map.remove("dlx_ver").unwrap(),
+            assets_map.remove("spr").unwrap(),
+            checksums_asset,
+            signature_asset,
+            mirrors_asset
+        );
+        return Ok(data);
+    }
+
+    /// Returns the assets we may need to download
+    fn get_assets_mut(&mut self) -> Vec<&mut GHAsset> {
+        let mut assets = vec![&mut self.def_ver_asset, &mut self.spr_asset];
+        assets.extend(self.checksums_asset.as_mut());
+        assets.extend(self.signature_asset.as_mut());
+        return assets;
+    }
+
+    /// Adds mirrors to the assets, the ones from the settings go first, then the ones from the manifest
+    /// This is "best-effort", if we can't get the manifest we just use what we have
+    /// Releases without checksums only come from GitHub, we couldn't tell if a mirror has changed them
+    pub fn add_mirrors(&mut self, client: &reqwest::Client, settings: &Settings) {
+        if self.checksums_asset.is_none() {
+            return;
+        }
+        let tag = self.version.clone();
+        for asset in self.get_assets_mut() {
+            asset.add_mirrors(settings, settings.get_mirror_urls(&tag, &asset.name));
+        }
+        if let Some(ref mut mirrors_asset) = self.mirrors_asset {
+            mirrors_asset.add_mirrors(settings, settings.get_mirror_urls(&tag, &mirrors_asset.name));
+        }
+
+        let manifest = match self.mirrors_asset {
+            Some(ref mirrors_asset) => match request_mirrors_manifest(client, mirrors_asset) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    eprintln!("Failed to get the mirrors manifest: {e}");
+                    return;
+                }
+            },
+            None => return
+        };
+        for asset in self.get_assets_mut() {
+            if let Some(urls) = manifest.get(&asset.name) {
+                asset.add_mirrors(settings, urls.clone());
+            }
+        }
+    }
+}
+
+/// Release archives the user downloaded by hand
+#[derive(Debug, Clone)]
+pub struct LocalArchives {
+    pub game: PathBuf,
+    pub spritepacks: Option<PathBuf>
+}
+
+impl LocalArchives {
+    /// Sorts the given files into the game and spritepacks archives using the release assets names
+    pub fn from_paths(paths: &[PathBuf]) -> Result<Self, InstallError> {
+        let mut game = None;
+        let mut spritepacks = None;
+
+        for path in paths {
+            let name = path.file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let asset_key = crate::ASSETS_NAMES_RE_MAP.iter()
+                .find(|(_, re)| re.is_match(name))
+                .map(|(k, _)| *k);
+
+            match asset_key {
+                Some("def_ver") => game = Some(path.clone()),
+                Some("spr") => spritepacks = Some(path.clone()),
+                _ => return Err(InstallError::InvalidArchiveName(path.display().to_string()))
+            };
+
+            if !path.is_file() {
+                return Err(InstallError::IOError(
+                    io::Error::new(io::ErrorKind::NotFound, format!("'{}' is not a file", path.display()))
+                ));
+            }
+        }
+
+        let game = game.ok_or(InstallError::MissingArchive("game"))?;
+        return Ok(Self { game, spritepacks });
+    }
+}
+
+
+/// Represents an attachment in a GitHub release
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GHAsset {
+    pub name: String,
+    pub size: ContentSize,
+    pub browser_download_url: String,
+    /// Other URLs we can get the asset from, in the order we try them
+    #[serde(skip)]
+    pub mirror_urls: Vec<String>
+}
+
+impl GHAsset {
+    /// Returns the URLs we can download this asset from, GitHub goes first
+    pub fn get_urls(&self) -> Vec<&str> {
+        let mut urls = vec![self.browser_download_url.as_str()];
+        urls.extend(self.mirror_urls.iter().map(|url| url.as_str()));
+        return urls;
+    }
+
+    /// Adds mirrors of this asset, skipping the URLs we can't access or already have
+    fn add_mirrors(&mut self, settings: &Settings, urls: Vec<String>) {
+        for url in urls {
+            if !settings.is_allowed_url(&url) {
+                eprintln!("Ignoring mirror '{url}', it's not allowed by the settings");
+                continue;
+            }
+            if !self.get_urls().contains(&url.as_str()) {
+                self.mirror_urls.push(url);
+            }
+        }
+    }
+
+    /// Check if this asset can be downloaded, https is required unless the settings allow insecure URLs
+    pub fn is_downloadable(&self, settings: &Settings) -> bool {
+        return {
+            !self.name.is_empty()
+            && self.size != 0
+            && !self.browser_download_url.is_empty()
+            && settings.is_allowed_url(&self.browser_download_url)
+        };
+    }
+
+    /// Check if this asset is a valid release archive
+    pub fn is_valid(&self, settings: &Settings) -> bool {
+        return self.is_downloadable(settings) && self.browser_download_url.ends_with(".zip");
+    }
+}
+
+/// Represents a GitHub release
+#[derive(Serialize, Deserialize, Debug)]
+struct GHRelease {
+    tag_name: String,
+    name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    assets: Vec<GHAsset>
+}
+
+impl GHRelease {
+    /// Check if this release is valid
+    pub fn is_valid(&self) -> bool {
+        return {
+            !self.tag_name.is_empty()
+            && !self.name.is_empty()
+            && self.assets.len() != 0
+        };
+    }
+}
+
+
+/// Checks if the API has refused the request because of the rate limit
+fn is_rate_limited(resp: &reqwest::Response) -> bool {
+    let status_code = resp.status();
+    if status_code == StatusCode::TOO_MANY_REQUESTS {
+        return true;
+    }
+    if status_code != StatusCode::FORBIDDEN {
+        return false;
+    }
+    // The secondary rate limit comes with Retry-After instead
+    let is_exhausted = resp.headers().get(RATE_LIMIT_REMAINING_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.trim() == "0");
+
+    return is_exhausted || resp.headers().contains_key(headers::RETRY_AFTER);
+}
+
+/// Returns when the rate limit resets
+fn get_rate_limit_reset(resp: &reqwest::Response) -> Option<SystemTime> {
+    if let Some(retry_after) = retry::get_retry_after(resp) {
+        return Some(SystemTime::now() + retry_after);
+    }
+    let reset: u64 = resp.headers().get(RATE_LIMIT_RESET_HEADER)?.to_str().ok()?.trim().parse().ok()?;
+
+    return Some(UNIX_EPOCH + Duration::from_secs(reset));
+}
+
+/// Sends a request to the API, with the token from the settings if there's one
+/// Rate limit responses are turned into RateLimited so they aren't parsed as data
+fn send_api_request(req: reqwest::RequestBuilder, settings: &Settings) -> Result<reqwest::Response, InstallError> {
+    let req = match settings.github_token {
+        Some(ref token) => req.bearer_auth(token.trim()),
+        None => req
+    };
+    let resp = req.send()?;
+
+    if is_rate_limited(&resp) {
+        return Err(InstallError::RateLimited(get_rate_limit_reset(&resp)));
+    }
+
+    return Ok(resp);
+}
+
+/// Requests a single release from the given API URL
+/// Returns None if there's no such release
+fn request_release(
+    client: &reqwest::Client,
+    settings: &Settings,
+    url: &str
+) -> Result<Option<GHRelease>, InstallError> {
+    let resp = send_api_request(client.get(url), settings)?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let data = resp.error_for_status()?.bytes()?;
+
+    return Ok(Some(serde_json::from_slice(&data)?));
+}
+
+/// Requests the mirrors manifest, it maps asset names to lists of URLs
+fn request_mirrors_manifest(
+    client: &reqwest::Client,
+    asset: &GHAsset
+) -> Result<HashMap<String, Vec<String>>, DownloadError> {
+    let text = checksum::request_text_asset(client, asset)?;
+    return serde_json::from_str(&text)
+        .map_err(|e| DownloadError::IOError(e.into()));
+}
+
+/// Requests all published releases, newest first
+fn request_all_releases(client: &reqwest::Client, settings: &Settings) -> Result<Vec<GHRelease>, InstallError> {
+    let url = format!("{}/releases", settings.get_repo_api_url());
+    let mut releases = Vec::new();
+
+    for page in 1..=MAX_RELEASES_PAGES {
+        let req = client.get(&url)
+            .query(&[("per_page", RELEASES_PER_PAGE), ("page", page)]);
+        let data = send_api_request(req, settings)?
+            .error_for_status()?
+            .bytes()?;
+        let page_releases: Vec<GHRelease> = serde_json::from_slice(&data)?;
+        let total = page_releases.len();
+
+        releases.extend(page_releases.into_iter().filter(|r| !r.draft));
+
+        // A short page is the last one
+        if total < RELEASES_PER_PAGE {
+            break;
+        }
+    }
+
+    return Ok(releases);
+}
+
+/// Returns the API URL of the release with the given tag, the tag is percent-encoded as a path segment
+fn get_tag_url(settings: &Settings, tag: &str) -> Result<String, InstallError> {
+    let mut url = Url::parse(&format!("{}/releases/tags", settings.get_repo_api_url()))
+        .map_err(|_| SettingsError::InvalidValue("api_base_url"))?;
+    url.path_segments_mut()
+        .map_err(|_| SettingsError::InvalidValue("api_base_url"))?
+        .push(tag);
+
+    return Ok(url.into());
+}
+
+/// Requests data for the selected release from github (or the API set in the settings)
+pub fn get_release_data(
+    client: &reqwest::Client,
+    settings: &Settings,
+    selector: &ReleaseSelector
+) -> Result<ReleaseData, InstallError> {
+    let repo_url = settings.get_repo_api_url();
+
+    let mut data = match selector {
+        ReleaseSelector::Latest => {
+            let release = request_release(client, settings, &format!("{repo_url}/releases/latest"))?
+                .ok_or(InstallError::ReleaseNotFound("latest".to_string()))?;
+            ReleaseData::from_release(release, settings)?
+        },
+        ReleaseSelector::LatestPrerelease => {
+            // Skip the releases without the assets we need
+            request_all_releases(client, settings)?
+                .into_iter()
+                .find_map(|r| ReleaseData::from_release(r, settings).ok())
+                .ok_or(InstallError::ReleaseNotFound("latest pre-release".to_string()))?
+        },
+        ReleaseSelector::Tag(tag) => {
+            let release = request_release(client, settings, &get_tag_url(settings, tag)?)?
+                .ok_or_else(|| InstallError::ReleaseNotFound(tag.clone()))?;
+            ReleaseData::from_release(release, settings)?
+        }
+    };
+    data.add_mirrors(client, settings);
+
+    return Ok(data);
+}
+
+/// Returns all releases that have the assets we need, newest first
+pub fn list_releases(client: &reqwest::Client, settings: &Settings) -> Result<Vec<ReleaseData>, InstallError> {
+    let releases = request_all_releases(client, settings)?
+        .into_iter()
+        .filter_map(|r| ReleaseData::from_release(r, settings).ok())
+        .collect();
+
+    return Ok(releases);
+}
@@ -0,0 +1,40 @@
+/// The module with the interface the installer uses to report progress
+/// This keeps the install logic independent from whatever drives it (GUI, CLI, etc)
+
+use std::path::Path;
+
+
+/// Events the installer reports while running
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InstallEvent {
+    /// Progress of the current stage, from 0.0 to 1.0
+    Progress(f64),
+    Preparing,
+    Downloading,
+    Extracting,
+    DownloadingSpr,
+    ExtractingSpr,
+    /// Moving the extracted files into the game dir
+    Installing,
+    CleaningUp,
+    Done,
+    /// Removing the installed files and restoring the backups
+    Uninstalling
+}
+
+
+/// Receives installer events and tells the installer when to stop
+pub trait Reporter {
+    /// Handles an event sent by the installer
+    fn report(&self, event: InstallEvent);
+
+    /// Handles the path of the backup with the files the install has replaced,
+    /// it's reported right before the install is done
+    fn report_backup(&self, _path: &Path) {}
+
+    /// Returns true if the installation should be aborted,
+    /// the installer polls this between its steps
+    fn is_aborted(&self) -> bool {
+        return false;
+    }
+}
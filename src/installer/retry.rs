@@ -0,0 +1,154 @@
+/// The module that implements retrying failed requests with exponential backoff
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+    thread,
+    time::{Duration, SystemTime}
+};
+
+use ::reqwest::{
+    blocking as reqwest,
+    header as headers,
+    StatusCode
+};
+
+use crate::errors::DownloadError;
+
+
+// How many times we try a request before giving up
+const MAX_ATTEMPTS: u32 = 6;
+// The delay before the first retry, it doubles after each attempt
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+// We'd rather fail than make the user wait longer than this
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+// How often we check for abort while waiting
+const WAIT_STEP: Duration = Duration::from_millis(100);
+
+
+/// A failed attempt, with how long the server has asked us to wait
+#[derive(Debug)]
+pub struct FailedAttempt {
+    pub error: DownloadError,
+    pub retry_after: Option<Duration>
+}
+
+impl From<DownloadError> for FailedAttempt {
+    fn from(err: DownloadError) -> Self {
+        return Self { error: err, retry_after: None };
+    }
+}
+impl From<::reqwest::Error> for FailedAttempt {
+    fn from(err: ::reqwest::Error) -> Self {
+        return DownloadError::from(err).into();
+    }
+}
+impl From<io::Error> for FailedAttempt {
+    fn from(err: io::Error) -> Self {
+        return DownloadError::from(err).into();
+    }
+}
+
+
+/// Checks if the request error is a network failure
+fn is_transient_request_error(err: &::reqwest::Error) -> bool {
+    return err.is_timeout() || err.is_connect() || err.is_request() || err.is_body() || err.is_decode();
+}
+
+/// Checks if the error is likely to go away if we try again
+fn is_transient(error: &DownloadError) -> bool {
+    return match error {
+        DownloadError::RequestError(err) => is_transient_request_error(err),
+        DownloadError::IOError(err) => {
+            // Errors while reading the body come as IO errors
+            let is_request_error = err.get_ref()
+                .and_then(|inner| inner.downcast_ref::<::reqwest::Error>())
+                .map_or(false, is_transient_request_error);
+            is_request_error || matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::Interrupted
+            )
+        },
+        DownloadError::InvalidStatusCode(code) => {
+            *code == StatusCode::REQUEST_TIMEOUT
+            || *code == StatusCode::TOO_MANY_REQUESTS
+            || code.is_server_error()
+        },
+        // The server has closed the connection early
+        DownloadError::SizeMismatch(..) => true,
+        _ => false
+    };
+}
+
+/// Returns how long the server wants us to wait, Retry-After can be in seconds or an HTTP date
+pub fn get_retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let value = resp.headers().get(headers::RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+
+    return Some(date.duration_since(SystemTime::now()).unwrap_or_default());
+}
+
+/// Returns the delay before the given retry (starting from 0), this is exponential with "equal jitter"
+fn get_delay(retry: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << retry.min(16)).min(MAX_DELAY);
+    // RandomState is seeded randomly, good enough for jitter without pulling in a crate
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = (random % 1000) as f64 / 1000.0;
+
+    return delay/2 + (delay/2).mul_f64(jitter);
+}
+
+/// Sleeps for the given duration, returns false if we've been aborted while waiting
+fn wait(duration: Duration, is_aborted: &dyn Fn() -> bool) -> bool {
+    let mut waited = Duration::ZERO;
+    while waited < duration {
+        if is_aborted() {
+            return false;
+        }
+        let step = WAIT_STEP.min(duration - waited);
+        thread::sleep(step);
+        waited += step;
+    }
+    return !is_aborted();
+}
+
+/// Runs the operation until it succeeds, transient failures are retried with exponential backoff
+/// The operation should keep its progress between attempts, so it can continue from where it has failed
+pub fn with_retries<T>(
+    is_aborted: &dyn Fn() -> bool,
+    mut operation: impl FnMut() -> Result<T, FailedAttempt>
+) -> Result<T, DownloadError> {
+    let mut retry = 0;
+    loop {
+        let attempt = match operation() {
+            Ok(rv) => return Ok(rv),
+            Err(attempt) => attempt
+        };
+
+        if retry + 1 >= MAX_ATTEMPTS || !is_transient(&attempt.error) {
+            return Err(attempt.error);
+        }
+        let delay = match attempt.retry_after {
+            Some(retry_after) if retry_after > MAX_RETRY_AFTER => return Err(attempt.error),
+            Some(retry_after) => retry_after,
+            None => get_delay(retry)
+        };
+
+        eprintln!("Request failed: {}, retrying in {:.1}s", attempt.error, delay.as_secs_f64());
+        if !wait(delay, is_aborted) {
+            return Err(attempt.error);
+        }
+        retry += 1;
+    }
+}
@@ -0,0 +1,18 @@
+/// The module that implements verifying release signatures
+
+use minisign_verify::{PublicKey, Signature};
+
+use crate::errors::SignatureError;
+
+
+/// Verifies the minisign signature of the given data
+pub fn verify(public_key: &str, data: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let public_key = PublicKey::from_base64(public_key.trim())
+        .map_err(SignatureError::InvalidPublicKey)?;
+    let signature = Signature::decode(signature)
+        .map_err(SignatureError::InvalidSignature)?;
+
+    // Legacy signatures aren't prehashed, there's no reason to accept them
+    return public_key.verify(data, &signature, false)
+        .map_err(SignatureError::InvalidSignature);
+}
@@ -0,0 +1,371 @@
+/// The module that defends the install against symlinks
+/// A symlinked dir in the DDLC dir (or a symlink in an archive) could make us write files anywhere,
+/// so every path we install to is checked against the symlink policy from the settings
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf}
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{errors::ExtractionError, settings::SymlinkPolicy};
+use super::{checksum::HashingWriter, manifest::InstalledFile};
+
+
+// The file type bits of a unix mode, and their value for symlinks
+const FILE_TYPE_MASK: u32 = 0o170000;
+const SYMLINK_FILE_TYPE: u32 = 0o120000;
+/// Sanity limit for the target of a symlink in an archive
+const MAX_LINK_TARGET_SIZE: u64 = 4096;
+
+
+/// A symlink we've replaced with a real dir
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplacedSymlink {
+    pub path: PathBuf,
+    /// Where it pointed to
+    pub target: PathBuf
+}
+
+
+/// Checks if an archive entry with the given unix mode is a symlink
+pub fn is_symlink(mode: Option<u32>) -> bool {
+    return mode.map_or(false, |mode| mode & FILE_TYPE_MASK == SYMLINK_FILE_TYPE);
+}
+
+/// Returns the dirs on the way from the base dir to the given path that are symlinks, the closest to the base first
+/// The path itself isn't checked, we rename files into place, which replaces a symlink rather than writing thru it
+fn find_symlinked_dirs(base: &Path, path: &Path) -> Vec<PathBuf> {
+    let mut rv = Vec::new();
+    let parent_dir = match path.parent().and_then(|parent_dir| parent_dir.strip_prefix(base).ok()) {
+        Some(parent_dir) => parent_dir,
+        None => return rv
+    };
+
+    let mut current = base.to_path_buf();
+    for component in parent_dir.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => rv.push(current.clone()),
+            Ok(_) => {},
+            // Then there's nothing in it either
+            Err(_) => break
+        };
+    }
+
+    return rv;
+}
+
+/// Checks the given path leads somewhere inside the base dir once the symlinks are resolved
+/// NOTE: base must be canonical, the parts of the path that don't exist yet can't be symlinks
+pub fn verify_inside(base: &Path, path: &Path) -> Result<(), ExtractionError> {
+    let existing_dir = path.ancestors()
+        .skip(1)
+        .find(|dir| dir.exists())
+        .ok_or_else(|| ExtractionError::OutsideDestination(path.display().to_string()))?;
+
+    if !fs::canonicalize(existing_dir)?.starts_with(base) {
+        return Err(ExtractionError::OutsideDestination(path.display().to_string()));
+    }
+    return Ok(());
+}
+
+/// Checks the path we're about to install a file to against the policy, base is the dir we trust
+/// With the replace policy, a symlinked dir on the way is replaced with an empty dir, the replaced link is returned
+pub fn secure_target_path(
+    base: &Path,
+    path: &Path,
+    policy: SymlinkPolicy
+) -> Result<Option<ReplacedSymlink>, ExtractionError> {
+    let symlinked_dirs = find_symlinked_dirs(base, path);
+    let link = match symlinked_dirs.first() {
+        Some(link) => link,
+        None => return Ok(None)
+    };
+
+    return match policy {
+        SymlinkPolicy::Refuse => {
+            Err(ExtractionError::SymlinkInDestination(link.display().to_string()))
+        },
+        SymlinkPolicy::Follow => {
+            verify_inside(&fs::canonicalize(base)?, path)?;
+            Ok(None)
+        },
+        // The links further down are in the one we replace, so they're gone with it
+        SymlinkPolicy::Replace => {
+            let target = fs::read_link(link)?;
+            remove_symlink(link)?;
+            fs::create_dir(link)?;
+            eprintln!("Replaced the symlink '{}' (to '{}') with a dir", link.display(), target.display());
+            Ok(Some(ReplacedSymlink { path: link.clone(), target }))
+        }
+    };
+}
+
+/// Puts back a symlink we've replaced, the dir that replaced it must be empty by now
+/// This is "best-effort", errors are only logged
+pub fn restore_symlink(link: &ReplacedSymlink) {
+    if link.path.is_dir() {
+        if let Err(e) = fs::remove_dir(&link.path) {
+            eprintln!("Failed to restore the symlink '{}': {e}", link.path.display());
+            return;
+        }
+    }
+    if let Err(e) = create_symlink(&link.target, &link.path) {
+        eprintln!("Failed to restore the symlink '{}': {e}", link.path.display());
+    }
+}
+
+/// Creates a symlink from an archive entry, it must lead somewhere inside the destination
+/// rel_path is the path of the entry relative to the destination, which must be canonical
+pub fn extract_symlink<R: Read>(
+    entry: &mut R,
+    destination: &Path,
+    rel_path: &Path,
+    manifest_path: String
+) -> Result<InstalledFile, ExtractionError> {
+    let mut target = String::new();
+    entry.take(MAX_LINK_TARGET_SIZE).read_to_string(&mut target)?;
+    let path = destination.join(rel_path);
+
+    // ".." goes up from where the dir of the link really is, which isn't its path if there's a symlink on the way
+    let goes_up = Path::new(&target).components().any(|component| component == Component::ParentDir);
+    let is_inside = is_relative_target_inside(rel_path, Path::new(&target))
+        && (!goes_up || find_symlinked_dirs(destination, &path).is_empty());
+    if !is_inside {
+        return Err(ExtractionError::OutsideDestination(rel_path.display().to_string()));
+    }
+    create_symlink(Path::new(&target), &path)?;
+
+    // For the manifest, the target is the content of a symlink
+    let mut hasher = HashingWriter::new(io::sink());
+    hasher.write_all(target.as_bytes())?;
+
+    return Ok(
+        InstalledFile { path: manifest_path, size: target.len() as u64, sha256: hasher.finalize() }
+    );
+}
+
+/// Checks if a symlink at the given relative path with the given target stays inside the dir the path is relative to
+/// ".." is only allowed at the start, after a component that's a symlink it would go up from where that leads
+fn is_relative_target_inside(rel_path: &Path, target: &Path) -> bool {
+    let mut depth = rel_path.components().count().saturating_sub(1);
+    let mut is_start = true;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => {
+                depth += 1;
+                is_start = false;
+            },
+            Component::CurDir => {},
+            Component::ParentDir if depth > 0 && is_start => depth -= 1,
+            // Going above the dir, ".." after a name or absolute paths
+            _ => return false
+        };
+    }
+    return true;
+}
+
+/// Removes a symlink, not what it points to
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    // Symlinks to dirs are dirs on windows
+    return fs::remove_file(path).or_else(|_| fs::remove_dir(path));
+}
+
+/// Creates a symlink at the given path
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+    return std::os::unix::fs::symlink(target, path);
+}
+
+/// Creates a symlink at the given path
+/// NOTE: this needs the developer mode or admin rights
+#[cfg(windows)]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+    let is_dir = path.parent().map_or(false, |parent_dir| parent_dir.join(target).is_dir());
+    return match is_dir {
+        true => std::os::windows::fs::symlink_dir(target, path),
+        false => std::os::windows::fs::symlink_file(target, path)
+    };
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io::{Cursor, Write},
+        os::unix::fs::symlink,
+        path::{Path, PathBuf},
+        process
+    };
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::{is_relative_target_inside, restore_symlink, secure_target_path};
+    use crate::{
+        errors::ExtractionError,
+        installer::{extract_archive, reporter::{InstallEvent, Reporter}},
+        settings::{Settings, SymlinkPolicy}
+    };
+
+
+    struct NullReporter;
+
+    impl Reporter for NullReporter {
+        fn report(&self, _event: InstallEvent) {}
+    }
+
+    /// Returns an empty dir for a test, it's canonical
+    fn get_test_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("jn-installer-test-{}-{name}", process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+
+        return fs::canonicalize(path).unwrap();
+    }
+
+    /// Writes an archive with the given entries into the given path, they're put in an outer dir like in the releases
+    /// Entries with a target are symlinks, names ending with '/' are dirs
+    fn write_archive(path: &Path, entries: &[(&str, Option<&str>)]) {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, target) in entries {
+            let name = format!("JN/{name}");
+            if name.ends_with('/') {
+                writer.add_directory(name, FileOptions::default()).unwrap();
+                continue;
+            }
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(target.unwrap_or("data").as_bytes()).unwrap();
+        }
+        let mut data = writer.finish().unwrap().into_inner();
+
+        // The zip crate can't write symlinks, so the file type in the central directory is changed
+        let mut pos = data.windows(4).position(|window| window == [0x50, 0x4b, 0x01, 0x02]).unwrap();
+        for (_, target) in entries {
+            let name_len = u16::from_le_bytes([data[pos + 28], data[pos + 29]]) as usize;
+            let extra_len = u16::from_le_bytes([data[pos + 30], data[pos + 31]]) as usize;
+            let comment_len = u16::from_le_bytes([data[pos + 32], data[pos + 33]]) as usize;
+            if target.is_some() {
+                data[pos + 38..pos + 42].copy_from_slice(&(0o120777u32 << 16).to_le_bytes());
+            }
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        fs::write(path, data).unwrap();
+    }
+
+    fn extract(dir: &Path, entries: &[(&str, Option<&str>)], policy: SymlinkPolicy) -> Result<PathBuf, ExtractionError> {
+        let archive_path = dir.join("archive.zip");
+        write_archive(&archive_path, entries);
+        let settings = Settings { symlink_policy: policy, ..Settings::default() };
+        let destination = dir.join("out");
+        extract_archive(&NullReporter, &File::open(&archive_path).unwrap(), &destination, &settings)?;
+
+        return Ok(destination);
+    }
+
+    #[test]
+    fn test_relative_target_inside() {
+        assert!(is_relative_target_inside(Path::new("link"), Path::new("file")));
+        assert!(is_relative_target_inside(Path::new("link"), Path::new("./game/file")));
+        assert!(is_relative_target_inside(Path::new("game/link"), Path::new("../file")));
+        assert!(is_relative_target_inside(Path::new("game/sub/link"), Path::new("../../file")));
+
+        // "../" escapes
+        assert!(!is_relative_target_inside(Path::new("link"), Path::new("../file")));
+        assert!(!is_relative_target_inside(Path::new("game/link"), Path::new("../../file")));
+        assert!(!is_relative_target_inside(Path::new("game/link"), Path::new("../game/../../file")));
+        // Absolute targets
+        assert!(!is_relative_target_inside(Path::new("link"), Path::new("/etc/passwd")));
+        assert!(!is_relative_target_inside(Path::new("game/link"), Path::new("/")));
+        // ".." after a name, it could be a symlink
+        assert!(!is_relative_target_inside(Path::new("link"), Path::new("./a/../..")));
+        assert!(!is_relative_target_inside(Path::new("game/link"), Path::new("./a/../file")));
+    }
+
+    #[test]
+    fn test_secure_target_path() {
+        let dir = get_test_dir("secure-target");
+        let base = dir.join("ddlc");
+        let outside = dir.join("outside");
+        fs::create_dir_all(base.join("real")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        symlink("real", base.join("inside_link")).unwrap();
+        symlink(&outside, base.join("outside_link")).unwrap();
+
+        // Nothing to do without symlinks on the way, the file itself doesn't matter
+        for policy in [SymlinkPolicy::Refuse, SymlinkPolicy::Follow, SymlinkPolicy::Replace] {
+            assert!(secure_target_path(&base, &base.join("real/file"), policy).unwrap().is_none());
+            assert!(secure_target_path(&base, &base.join("inside_link"), policy).unwrap().is_none());
+        }
+
+        let rv = secure_target_path(&base, &base.join("inside_link/file"), SymlinkPolicy::Refuse);
+        assert!(matches!(rv, Err(ExtractionError::SymlinkInDestination(_))), "{rv:?}");
+
+        assert!(secure_target_path(&base, &base.join("inside_link/file"), SymlinkPolicy::Follow).unwrap().is_none());
+        let rv = secure_target_path(&base, &base.join("outside_link/file"), SymlinkPolicy::Follow);
+        assert!(matches!(rv, Err(ExtractionError::OutsideDestination(_))), "{rv:?}");
+
+        let link = secure_target_path(&base, &base.join("outside_link/sub/file"), SymlinkPolicy::Replace)
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.path, base.join("outside_link"));
+        assert_eq!(link.target, outside);
+        assert!(!fs::symlink_metadata(&link.path).unwrap().file_type().is_symlink());
+        restore_symlink(&link);
+        assert_eq!(fs::read_link(base.join("outside_link")).unwrap(), outside);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_symlinks() {
+        let dir = get_test_dir("extract-symlinks");
+        let entries = [("game/script.rpy", None), ("game/link.rpy", Some("script.rpy")), ("up", Some("game/../.."))];
+
+        let rv = extract(&dir, &entries[..2], SymlinkPolicy::Refuse);
+        assert!(matches!(rv, Err(ExtractionError::SymlinkEntry(ref name)) if name == "JN/game/link.rpy"), "{rv:?}");
+
+        let destination = extract(&dir, &entries[..2], SymlinkPolicy::Follow).unwrap();
+        assert_eq!(fs::read_link(destination.join("game/link.rpy")).unwrap(), Path::new("script.rpy"));
+        fs::remove_dir_all(&destination).unwrap();
+
+        let rv = extract(&dir, &entries, SymlinkPolicy::Follow);
+        assert!(matches!(rv, Err(ExtractionError::OutsideDestination(_))), "{rv:?}");
+        fs::remove_dir_all(dir.join("out")).unwrap();
+
+        // Replaced symlinks are plain files with their target
+        let destination = extract(&dir, &entries, SymlinkPolicy::Replace).unwrap();
+        assert_eq!(fs::read_to_string(destination.join("up")).unwrap(), "game/../..");
+        fs::remove_dir_all(&destination).unwrap();
+
+        // "sub/.." would be the DDLC dir, so "../" from the link in there would escape
+        let entries = [("game/", None), ("game/sub", Some(".")), ("game/sub/link", Some("../file"))];
+        let rv = extract(&dir, &entries, SymlinkPolicy::Follow);
+        assert!(matches!(rv, Err(ExtractionError::OutsideDestination(_))), "{rv:?}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_checks_paths_before_creating_dirs() {
+        let dir = get_test_dir("extract-outside");
+        let outside = dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(dir.join("out")).unwrap();
+        symlink(&outside, dir.join("out/game")).unwrap();
+
+        for entries in [[("game/new_dir/", None)], [("game/new_dir/file", None)]] {
+            let rv = extract(&dir, &entries, SymlinkPolicy::Follow);
+            assert!(matches!(rv, Err(ExtractionError::OutsideDestination(_))), "{rv:?}");
+            assert!(!outside.join("new_dir").exists());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+/// The module that restores the modification times of extracted files and dirs
+/// Ren'Py rebuilds the scripts by their mtimes, so they should match the release, not the install
+
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf}
+};
+
+use filetime::FileTime;
+use zip::read::ZipFile;
+
+
+/// Id of the extended timestamp extra field, it has the mtime in UTC
+const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+// The mtime is in the field if this flag is set
+const MTIME_FLAG: u8 = 1;
+
+
+/// Returns the modification time of an archive entry
+/// The extended timestamp is used if the entry has one, otherwise the DOS time of the entry
+/// NOTE: DOS times have no timezone, so they're treated as UTC
+pub fn get_entry_mtime(file: &ZipFile) -> Option<FileTime> {
+    if let Some(mtime) = read_extended_mtime(file.extra_data()) {
+        return Some(FileTime::from_unix_time(mtime, 0));
+    }
+
+    return file.last_modified()
+        .to_time()
+        .ok()
+        .map(|time| FileTime::from_unix_time(time.unix_timestamp(), 0));
+}
+
+/// Reads the mtime from the extended timestamp field in the given extra data, if it's there
+fn read_extended_mtime(mut extra_data: &[u8]) -> Option<i64> {
+    while extra_data.len() >= 4 {
+        let id = u16::from_le_bytes([extra_data[0], extra_data[1]]);
+        let size = u16::from_le_bytes([extra_data[2], extra_data[3]]) as usize;
+        let data = extra_data.get(4..4 + size)?;
+
+        if id == EXTENDED_TIMESTAMP_ID {
+            if data.len() < 5 || data[0] & MTIME_FLAG == 0 {
+                return None;
+            }
+            return Some(i32::from_le_bytes([data[1], data[2], data[3], data[4]]) as i64);
+        }
+        extra_data = &extra_data[4 + size..];
+    }
+
+    return None;
+}
+
+/// Sets the modification time of a file or dir, this is "best-effort", errors are only logged
+pub fn set_mtime(path: &Path, mtime: FileTime) {
+    if let Err(e) = filetime::set_file_mtime(path, mtime) {
+        eprintln!("Failed to set the modification time of '{}': {e}", path.display());
+    }
+}
+
+/// Returns the modification times of the dirs in the given staging dir,
+/// the paths are moved to the target dir
+pub fn get_dir_mtimes(staging_dir: &Path, target_dir: &Path) -> Vec<(PathBuf, FileTime)> {
+    let mut rv = Vec::new();
+    let entries = match read_dir(staging_dir) {
+        Ok(entries) => entries,
+        Err(_) => return rv
+    };
+
+    for entry in entries.flatten() {
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => continue
+        };
+        let target_path = target_dir.join(entry.file_name());
+        rv.extend(get_dir_mtimes(&entry.path(), &target_path));
+        rv.push((target_path, FileTime::from_last_modification_time(&metadata)));
+    }
+
+    return rv;
+}
@@ -0,0 +1,447 @@
+/// The module that implements installing thru a staging dir
+/// The archives are extracted into the staging dir first, then the files are moved into place,
+/// if anything fails on the way, the game dir is put back the way it was
+/// The commit is recorded in a journal, so an install that's been killed halfway can be rolled back later
+/// The manifest is written into the staging dir and only moved into place once the journal is gone,
+/// so an interrupted commit is either rolled back to the old manifest or finished with the new one
+
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::{Path, PathBuf}
+};
+
+use filetime::FileTime;
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    errors::{ExtractionError, InstallError},
+    settings::{Settings, SymlinkPolicy},
+    utils
+};
+use super::{
+    backup::{self, Backup},
+    get_component_dir,
+    get_game_dir,
+    join_relative_path,
+    manifest::{self, InstallManifest},
+    move_file,
+    prepare_game_dir,
+    read_previous_manifest,
+    remove_empty_dirs,
+    reporter::{InstallEvent, Reporter},
+    symlinks::{self, ReplacedSymlink},
+    timestamps
+};
+
+
+/// Name of the staging dir, it's in the game dir, so it's on the same filesystem
+/// NOTE: not in game/, Ren'Py would load the scripts from there
+pub const STAGING_DIR_NAME: &str = "jn_staging";
+/// Name of the commit journal, it's in the staging dir while the files are being moved
+const JOURNAL_FILE_NAME: &str = "jn_commit.json";
+/// Name a journal we can't read is moved to, it's in the backups dir
+const BROKEN_JOURNAL_FILE_NAME: &str = "jn_commit_broken.json";
+
+
+/// What a commit is going to change, it's written before anything is moved
+/// If it's still there when the next install begins, the commit has been interrupted
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CommitJournal {
+    /// Path of the backup dir relative to the game dir, with '/' separators
+    backup_dir: String,
+    /// Components and paths of the files that are moved into place
+    files: Vec<(String, String)>,
+    replaced_symlinks: Vec<ReplacedSymlink>
+}
+
+
+/// An install that hasn't changed the game dir yet
+pub struct Transaction {
+    extraction_dir: PathBuf,
+    staging_dir: PathBuf,
+    // Whether the dirs get the modification times of the staged dirs
+    preserve_timestamps: bool,
+    symlink_policy: SymlinkPolicy
+}
+
+impl Transaction {
+    /// Starts an install into the given DDLC dir, leftovers of an interrupted install are removed,
+    /// if it's been interrupted while moving the files, the game dir is rolled back first
+    pub fn begin(extraction_dir: &Path, settings: &Settings) -> io::Result<Self> {
+        let staging_dir = get_game_dir(extraction_dir).join(STAGING_DIR_NAME);
+        if staging_dir.exists() {
+            recover_interrupted_commit(extraction_dir, &staging_dir)?;
+            fs::remove_dir_all(&staging_dir)?;
+        }
+        fs::create_dir_all(&staging_dir)?;
+
+        return Ok(
+            Self {
+                extraction_dir: extraction_dir.to_path_buf(),
+                staging_dir,
+                preserve_timestamps: settings.preserve_timestamps,
+                symlink_policy: settings.symlink_policy
+            }
+        );
+    }
+
+    /// Returns the dir the given component should be extracted into
+    pub fn get_staging_dir(&self, component: &str) -> PathBuf {
+        return self.staging_dir.join(component);
+    }
+
+    /// Drops the install, the game dir stays untouched
+    pub fn discard(self) {
+        if let Err(e) = fs::remove_dir_all(&self.staging_dir) {
+            eprintln!("Failed to delete the staging dir '{}': {e}", self.staging_dir.display());
+        }
+    }
+
+    /// Moves the staged files of the manifest components into place, then writes the manifest
+    /// The files in the way are moved into a backup first
+    /// Returns false if the install has been aborted, the game dir is rolled back in that case,
+    /// the same as when this fails
+    pub fn commit(self, reporter: &dyn Reporter, manifest: InstallManifest) -> Result<bool, InstallError> {
+        reporter.report(InstallEvent::Installing);
+        reporter.report(InstallEvent::Progress(0.0));
+
+        let destination = get_game_dir(&self.extraction_dir);
+        let previous = read_previous_manifest(&destination);
+        let mut backup = Backup::new(&self.extraction_dir, previous.as_ref());
+        let dropped_files = get_dropped_files(previous.as_ref(), &manifest);
+        let mut moved_files = Vec::new();
+        let mut journal = CommitJournal {
+            backup_dir: backup.dir.clone(),
+            files: manifest.components.iter()
+                .flat_map(|component| {
+                    component.files.iter().map(move |file| (component.name.clone(), file.path.clone()))
+                })
+                .collect(),
+            replaced_symlinks: Vec::new()
+        };
+        // Moving the files out changes the mtimes of the staged dirs, so we get them first
+        let dir_mtimes = self.get_dir_mtimes(&manifest);
+
+        let rv = self.write_journal(&journal)
+            .and_then(|_| self.secure_target_paths(&manifest, &dropped_files, &mut journal))
+            .and_then(|_| self.move_files(reporter, &manifest, &mut backup, &mut moved_files))
+            .and_then(
+                |is_done| {
+                    if is_done {
+                        self.remove_dropped_files(&dropped_files, &mut backup)?;
+                    }
+                    return Ok(is_done);
+                }
+            )
+            .and_then(
+                |is_done| {
+                    if is_done {
+                        write_manifest(&destination, manifest, &backup, &self.staging_dir.join(manifest::MANIFEST_FILE_NAME))?;
+                        self.remove_journal()?;
+                    }
+                    return Ok(is_done);
+                }
+            );
+
+        return match rv {
+            Ok(true) => {
+                // The install is finished once the journal is gone, there's nothing to roll back anymore
+                // If the manifest can't be moved into place, the staging dir is kept, so the next install does it
+                publish_manifest(&self.staging_dir, &destination)?;
+                backup.finish();
+                if !backup.is_empty() {
+                    reporter.report_backup(&backup.get_path());
+                }
+                self.discard();
+                // Everything we've done changes the mtimes of the dirs, so they're set last
+                for (path, mtime) in dir_mtimes {
+                    if path.is_dir() {
+                        timestamps::set_mtime(&path, mtime);
+                    }
+                }
+                Ok(true)
+            },
+            Ok(false) => {
+                eprintln!("Rolling back the install...");
+                self.rollback(&backup, &moved_files, &journal.replaced_symlinks);
+                Ok(false)
+            },
+            Err(e) => {
+                eprintln!("Rolling back the install: {e}");
+                self.rollback(&backup, &moved_files, &journal.replaced_symlinks);
+                Err(e)
+            }
+        };
+    }
+
+    /// Returns the modification times the dirs of the manifest components should get,
+    /// it's empty if the timestamps aren't preserved
+    fn get_dir_mtimes(&self, manifest: &InstallManifest) -> Vec<(PathBuf, FileTime)> {
+        let mut rv = Vec::new();
+        if !self.preserve_timestamps {
+            return rv;
+        }
+
+        for component in &manifest.components {
+            if let Some(root) = get_component_dir(&self.extraction_dir, &component.name) {
+                rv.extend(timestamps::get_dir_mtimes(&self.get_staging_dir(&component.name), &root));
+            }
+        }
+
+        return rv;
+    }
+
+    /// Writes the journal into the staging dir, it's replaced atomically
+    fn write_journal(&self, journal: &CommitJournal) -> Result<(), InstallError> {
+        let data = serde_json::to_vec(journal)?;
+        return Ok(utils::write_file_atomically(&self.staging_dir.join(JOURNAL_FILE_NAME), &data)?);
+    }
+
+    /// Deletes the journal, after that the commit can't be rolled back anymore
+    fn remove_journal(&self) -> Result<(), InstallError> {
+        return Ok(fs::remove_file(self.staging_dir.join(JOURNAL_FILE_NAME))?);
+    }
+
+    /// Checks the paths of the manifest files and the dropped files against the symlink policy
+    /// before anything is moved, the symlinks replaced with dirs are recorded in the journal
+    fn secure_target_paths(
+        &self,
+        manifest: &InstallManifest,
+        dropped_files: &[(String, String)],
+        journal: &mut CommitJournal
+    ) -> Result<(), InstallError> {
+        let files = manifest.components.iter()
+            .flat_map(|component| component.files.iter().map(move |file| (&component.name, &file.path)))
+            .chain(dropped_files.iter().map(|(component, path)| (component, path)));
+
+        for (component, path) in files {
+            let root = get_component_dir(&self.extraction_dir, component)
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(component.clone()))?;
+            // The DDLC dir is the one the user picked, so we trust it, but not anything in it
+            let base = match component.as_str() {
+                manifest::COMPONENT_GAME => self.extraction_dir.clone(),
+                _ => root.parent().map_or_else(|| root.clone(), Path::to_path_buf)
+            };
+
+            let target = join_relative_path(&root, path)
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(path.clone()))?;
+            if let Some(link) = symlinks::secure_target_path(&base, &target, self.symlink_policy)? {
+                journal.replaced_symlinks.push(link);
+                self.write_journal(journal)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Moves the dropped files into the backup, they're deleted once the install is finished
+    fn remove_dropped_files(&self, dropped_files: &[(String, String)], backup: &mut Backup) -> Result<(), InstallError> {
+        for (component, path) in dropped_files {
+            let root = get_component_dir(&self.extraction_dir, component)
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(component.clone()))?;
+            let target = join_relative_path(&root, path)
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(path.clone()))?;
+
+            if target.is_file() {
+                backup.remove_file(component, path)?;
+                remove_empty_dirs(target.parent(), &root);
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Moves the staged files into place, the paths of the moved files are added to moved_files
+    /// Returns false if the install has been aborted
+    fn move_files(
+        &self,
+        reporter: &dyn Reporter,
+        manifest: &InstallManifest,
+        backup: &mut Backup,
+        moved_files: &mut Vec<(PathBuf, PathBuf)>
+    ) -> Result<bool, InstallError> {
+        prepare_game_dir(&get_game_dir(&self.extraction_dir), backup);
+
+        let total_files: usize = manifest.components.iter()
+            .map(|component| component.files.len())
+            .sum();
+        let mut done_files = 0;
+
+        for component in &manifest.components {
+            let staging_dir = self.get_staging_dir(&component.name);
+            let root = get_component_dir(&self.extraction_dir, &component.name)
+                .ok_or_else(|| ExtractionError::UnsafeFilepath(component.name.clone()))?;
+
+            for file in &component.files {
+                let paths = join_relative_path(&staging_dir, &file.path)
+                    .zip(join_relative_path(&root, &file.path));
+                let (source, target) = paths.ok_or_else(|| ExtractionError::UnsafeFilepath(file.path.clone()))?;
+
+                if target.is_file() {
+                    backup.remove_file(&component.name, &file.path)?;
+                }
+                move_file(&source, &target)?;
+                moved_files.push((root.clone(), target));
+
+                done_files += 1;
+                reporter.report(InstallEvent::Progress(done_files as f64 / total_files as f64));
+                if reporter.is_aborted() {
+                    return Ok(false);
+                }
+            }
+        }
+
+        return Ok(true);
+    }
+
+    /// Removes the files we've moved into place, restores the backup and the symlinks we've replaced
+    /// This is best-effort, what couldn't be undone is logged
+    fn rollback(self, backup: &Backup, moved_files: &[(PathBuf, PathBuf)], replaced_symlinks: &[ReplacedSymlink]) {
+        for (root, path) in moved_files.iter().rev() {
+            match fs::remove_file(path) {
+                Ok(_) => remove_empty_dirs(path.parent(), root),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+                Err(e) => eprintln!("Failed to delete '{}': {e}", path.display())
+            };
+        }
+
+        let report = backup.restore(&self.extraction_dir, false);
+        for failure in report.failures {
+            eprintln!("Failed to restore {failure}");
+        }
+        for link in replaced_symlinks.iter().rev() {
+            symlinks::restore_symlink(link);
+        }
+
+        self.discard();
+    }
+}
+
+
+/// Rolls back the commit recorded in the journal in the staging dir, if there's one
+/// The files that aren't staged anymore have been moved into place, so they're deleted,
+/// and everything in the backup dir has been moved out of the way, so it's restored
+/// Without a journal, a manifest in the staging dir means only moving it into place was left to do
+/// This is best-effort, what couldn't be undone is logged, only failing to move the manifest is an error,
+/// the staging dir has to be kept then
+fn recover_interrupted_commit(extraction_dir: &Path, staging_dir: &Path) -> io::Result<()> {
+    let journal_path = staging_dir.join(JOURNAL_FILE_NAME);
+    if !journal_path.is_file() {
+        if staging_dir.join(manifest::MANIFEST_FILE_NAME).is_file() {
+            eprintln!("Finishing an interrupted install...");
+            return publish_manifest(staging_dir, &get_game_dir(extraction_dir));
+        }
+        return Ok(());
+    }
+    eprintln!("Rolling back an interrupted install...");
+    let journal = match read_journal(&journal_path) {
+        Ok(journal) => journal,
+        Err(e) => {
+            set_aside_broken_journal(extraction_dir, &journal_path, e);
+            return Ok(());
+        }
+    };
+
+    for (component, path) in journal.files.iter().rev() {
+        let root = match get_component_dir(extraction_dir, component) {
+            Some(root) => root,
+            None => continue
+        };
+        let paths = join_relative_path(&staging_dir.join(component), path)
+            .zip(join_relative_path(&root, path));
+        let (source, target) = match paths {
+            Some(paths) => paths,
+            None => continue
+        };
+        if source.exists() || !target.is_file() {
+            continue;
+        }
+        match fs::remove_file(&target) {
+            Ok(_) => remove_empty_dirs(target.parent(), &root),
+            Err(e) => eprintln!("Failed to delete '{}': {e}", target.display())
+        };
+    }
+
+    match Backup::read_dir(extraction_dir, &journal.backup_dir) {
+        Ok(backup) => {
+            let report = backup.restore(extraction_dir, false);
+            for failure in report.failures {
+                eprintln!("Failed to restore {failure}");
+            }
+        },
+        Err(e) => eprintln!("Failed to read the backup '{}', the replaced files haven't been restored: {e}", journal.backup_dir)
+    };
+    for link in journal.replaced_symlinks.iter().rev() {
+        symlinks::restore_symlink(link);
+    }
+
+    // The staging dir is deleted right after, so it's not a big deal if this fails
+    if let Err(e) = fs::remove_file(&journal_path) {
+        eprintln!("Failed to delete the commit journal '{}': {e}", journal_path.display());
+    }
+    return Ok(());
+}
+
+/// Reads the commit journal
+fn read_journal(path: &Path) -> io::Result<CommitJournal> {
+    return Ok(serde_json::from_slice(&fs::read(path)?)?);
+}
+
+/// Moves a journal we can't read into the backups dir, so it isn't deleted with the staging dir, and tells where it is
+/// We don't know what the commit has changed without it, so nothing is rolled back,
+/// the files it has replaced are still in their backup
+fn set_aside_broken_journal(extraction_dir: &Path, journal_path: &Path, error: io::Error) {
+    eprintln!("Failed to read the commit journal, the interrupted install can't be rolled back: {error}");
+    let backups_dir = get_game_dir(extraction_dir).join(backup::BACKUPS_DIR_NAME);
+    let target = backups_dir.join(BROKEN_JOURNAL_FILE_NAME);
+
+    let rv = fs::create_dir_all(&backups_dir).and_then(|_| fs::rename(journal_path, &target));
+    match rv {
+        Ok(_) => eprintln!(
+            "The journal has been moved to '{}', the files the install has replaced are in '{}'",
+            target.display(),
+            backups_dir.display()
+        ),
+        Err(e) => eprintln!("Failed to move the journal to '{}': {e}", target.display())
+    };
+}
+
+/// Moves the manifest a commit has written into the staging dir into place
+fn publish_manifest(staging_dir: &Path, destination: &Path) -> io::Result<()> {
+    return fs::rename(staging_dir.join(manifest::MANIFEST_FILE_NAME), InstallManifest::get_path(destination));
+}
+
+/// Returns the components and paths of the files the previous install of the reinstalled components had,
+/// but the new one doesn't, Ren'Py would still load them and they wouldn't be tracked anymore
+fn get_dropped_files(previous: Option<&InstallManifest>, manifest: &InstallManifest) -> Vec<(String, String)> {
+    let mut rv = Vec::new();
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return rv
+    };
+
+    for component in &manifest.components {
+        let previous_component = match previous.get_component(&component.name) {
+            Some(previous_component) => previous_component,
+            None => continue
+        };
+        let paths: HashSet<&str> = component.files.iter().map(|file| file.path.as_str()).collect();
+        for file in &previous_component.files {
+            if !paths.contains(file.path.as_str()) {
+                rv.push((component.name.clone(), file.path.clone()));
+            }
+        }
+    }
+
+    return rv;
+}
+
+/// Records the backup (if anything has been backed up) and writes the manifest of the destination into the given path
+fn write_manifest(destination: &Path, mut manifest: InstallManifest, backup: &Backup, path: &Path) -> Result<(), InstallError> {
+    if !backup.is_empty() {
+        manifest.backups.push(backup.clone());
+    }
+    return manifest.write(destination, path);
+}
@@ -0,0 +1,118 @@
+/// The module that implements removing an install
+/// Only the files recorded in the install manifest are removed, then the backups are restored
+
+use std::{
+    fs,
+    io,
+    path::Path
+};
+
+use crate::errors::InstallError;
+use super::{
+    get_component_dir,
+    get_game_dir,
+    get_spritepacks_dir,
+    join_relative_path,
+    manifest::{self, InstallManifest},
+    remove_empty_dirs,
+    reporter::{InstallEvent, Reporter}
+};
+
+
+/// What an uninstall has done
+#[derive(Debug, Default)]
+pub struct UninstallReport {
+    pub removed_files: usize,
+    pub restored_files: usize,
+    /// The files we couldn't remove or restore, with the reasons
+    pub failures: Vec<String>
+}
+
+
+/// Uninstalls Just Natsuki from the given DDLC dir
+/// Spritepacks are kept unless remove_spritepacks is true, in which case the whole dir is deleted
+/// The manifest is kept if anything has failed, so the uninstall can be tried again
+/// Progress is reported to the given reporter
+pub fn uninstall(
+    reporter: &dyn Reporter,
+    extraction_dir: &Path,
+    remove_spritepacks: bool
+) -> Result<UninstallReport, InstallError> {
+    reporter.report(InstallEvent::Uninstalling);
+    reporter.report(InstallEvent::Progress(0.0));
+
+    let game_dir = get_game_dir(extraction_dir);
+    let manifest = InstallManifest::read(&game_dir)?.ok_or(InstallError::MissingManifest)?;
+    let mut report = UninstallReport::default();
+
+    // The backups are restored file by file too, so they count as much as the installed files
+    let total_files: usize = manifest.components.iter()
+        .map(|component| component.files.len())
+        .chain(manifest.backups.iter().map(|backup| backup.files.len()))
+        .sum();
+    let mut done_files = 0;
+    let mut report_progress = |files: usize| {
+        done_files += files;
+        reporter.report(InstallEvent::Progress(done_files as f64 / total_files.max(1) as f64));
+    };
+
+    for component in &manifest.components {
+        if component.name == manifest::COMPONENT_SPRITEPACKS && !remove_spritepacks {
+            report_progress(component.files.len());
+            continue;
+        }
+        let root = match get_component_dir(extraction_dir, &component.name) {
+            Some(root) => root,
+            None => {
+                eprintln!("Skipping unknown component '{}'", component.name);
+                report_progress(component.files.len());
+                continue;
+            }
+        };
+
+        for file in &component.files {
+            // Failed files are done too, they're in the report
+            report_progress(1);
+            let path = match join_relative_path(&root, &file.path) {
+                Some(path) => path,
+                None => {
+                    report.failures.push(format!("'{}': unsafe path", file.path));
+                    continue;
+                }
+            };
+            match fs::remove_file(&path) {
+                Ok(_) => report.removed_files += 1,
+                // Already gone, that's what we want anyway
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+                Err(e) => {
+                    report.failures.push(format!("'{}': {e}", path.display()));
+                    continue;
+                }
+            };
+            remove_empty_dirs(path.parent(), &root);
+        }
+    }
+
+    if remove_spritepacks {
+        let spritepacks_dir = get_spritepacks_dir(extraction_dir);
+        if spritepacks_dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&spritepacks_dir) {
+                report.failures.push(format!("'{}': {e}", spritepacks_dir.display()));
+            }
+        }
+    }
+
+    // Newest first, so the oldest backups (the files from before any install) win
+    for backup in manifest.backups.iter().rev() {
+        let restore_report = backup.restore(extraction_dir, !remove_spritepacks);
+        report.restored_files += restore_report.restored_files;
+        report.failures.extend(restore_report.failures);
+        report_progress(backup.files.len());
+    }
+
+    if report.failures.is_empty() {
+        fs::remove_file(InstallManifest::get_path(&game_dir))?;
+    }
+
+    return Ok(report);
+}
@@ -0,0 +1,159 @@
+/// The module that implements detecting an existing install and checking if it's up to date
+
+use std::{
+    cmp::Ordering,
+    fs,
+    path::Path
+};
+
+use crate::errors::InstallError;
+use super::{
+    get_game_dir,
+    get_spritepacks_dir,
+    manifest::{self, InstallManifest},
+    network,
+    release,
+    InstallOptions
+};
+
+
+// What the mod calls itself in options.rpy
+const MOD_NAME: &str = "Just Natsuki";
+
+
+/// An install of Just Natsuki we've found in a DDLC dir
+#[derive(Debug, Clone)]
+pub struct ExistingInstall {
+    /// None if we couldn't tell which version it is
+    pub version: Option<String>,
+    pub has_spritepacks: bool
+}
+
+/// How the installed version relates to the one we'd install
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateStatus {
+    NotInstalled,
+    /// There's an install, but we can't compare the versions
+    UnknownVersion,
+    UpToDate,
+    UpdateAvailable,
+    /// The installed version is newer, e.g. a pre-release
+    NewerInstalled
+}
+
+
+/// Returns the version of the install from its manifest, the archives names have it
+/// if it's been installed from local archives
+fn get_manifest_version(manifest: &InstallManifest) -> Option<String> {
+    if manifest.release_tag.is_some() {
+        return manifest.release_tag.clone();
+    }
+    let component = manifest.get_component(manifest::COMPONENT_GAME)?;
+
+    return crate::ARCHIVE_VERSION_RE.find(&component.archive)
+        .map(|found| found.as_str().to_string());
+}
+
+/// Looks for the name and version the mod defines in its options.rpy, this is for installs
+/// made before we had the manifest
+/// Returns None if options.rpy isn't ours, Some(None) if it's ours, but has no version
+fn read_version_marker(game_dir: &Path) -> Option<Option<String>> {
+    let data = fs::read_to_string(game_dir.join("game").join("options.rpy")).ok()?;
+
+    let name = crate::CONFIG_NAME_RE.captures(&data)?.get(1)?.as_str();
+    if !name.contains(MOD_NAME) {
+        return None;
+    }
+    let version = crate::CONFIG_VERSION_RE.captures(&data)
+        .and_then(|captures| captures.get(1))
+        .map(|version| version.as_str().to_string());
+
+    return Some(version);
+}
+
+/// Detects an install of Just Natsuki in the given DDLC dir
+/// The install manifest is used if there's one, otherwise the version markers in game/
+pub fn detect_install(extraction_dir: &Path) -> Option<ExistingInstall> {
+    let game_dir = get_game_dir(extraction_dir);
+
+    let manifest = InstallManifest::read(&game_dir).unwrap_or_else(
+        |e| {
+            eprintln!("Ignoring the install manifest: {e}");
+            None
+        }
+    );
+    if let Some(manifest) = manifest {
+        return Some(
+            ExistingInstall {
+                version: get_manifest_version(&manifest),
+                has_spritepacks: manifest.get_component(manifest::COMPONENT_SPRITEPACKS).is_some()
+            }
+        );
+    }
+
+    let version = read_version_marker(&game_dir)?;
+
+    return Some(
+        ExistingInstall {
+            version,
+            has_spritepacks: get_spritepacks_dir(extraction_dir).is_dir()
+        }
+    );
+}
+
+/// Parses a version like "v1.2.3" into its numbers, pre-release suffixes are ignored
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches(|c| c == 'v' || c == 'V');
+    let version = version.split(|c| c == '-' || c == '+').next()?;
+
+    return version.split('.')
+        .map(|part| part.parse().ok())
+        .collect();
+}
+
+/// Compares two versions, returns None if we can't tell which one is newer
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a, b) = match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => (a, b),
+        // At least we can tell if they're the same
+        _ if a.trim() == b.trim() => return Some(Ordering::Equal),
+        _ => return None
+    };
+
+    // Missing parts count as zeroes, so "1.2" is the same as "1.2.0"
+    let len = a.len().max(b.len());
+    for i in 0..len {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return Some(ordering);
+        }
+    }
+
+    return Some(Ordering::Equal);
+}
+
+/// Checks how the existing install relates to the given release version
+pub fn get_update_status(install: Option<&ExistingInstall>, release_version: &str) -> UpdateStatus {
+    let install = match install {
+        Some(install) => install,
+        None => return UpdateStatus::NotInstalled
+    };
+    let ordering = install.version.as_ref()
+        .and_then(|version| compare_versions(version, release_version));
+
+    return match ordering {
+        Some(Ordering::Less) => UpdateStatus::UpdateAvailable,
+        Some(Ordering::Equal) => UpdateStatus::UpToDate,
+        Some(Ordering::Greater) => UpdateStatus::NewerInstalled,
+        None => UpdateStatus::UnknownVersion
+    };
+}
+
+/// Returns the version of the release the options select, this needs network access
+pub fn get_release_version(options: &InstallOptions) -> Result<String, InstallError> {
+    options.settings.validate()?;
+    let client = network::build_client(&options.settings)?;
+    let data = release::get_release_data(&client, &options.settings, &options.release)?;
+
+    return Ok(data.version);
+}
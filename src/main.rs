@@ -3,13 +3,19 @@
 
 mod app;
 mod audio;
+mod cli;
 mod errors;
 mod installer;
+mod settings;
 mod static_data;
 mod utils;
 
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    env,
+    process
+};
 
 use regex::Regex;
 use lazy_static::lazy_static;
@@ -21,7 +27,8 @@ use const_format;
 const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 const DEF_VERSION: &str = "unknown build";
 
-// GH link parts to access the API
+// GH link parts to access the API, these are the defaults for the settings
+const API_BASE_URL: &str = "https://api.github.com";
 const ORG_NAME: &str = "Just-Natsuki-Team";
 const REPO_NAME: &str = "NatsukiModDev";
 
@@ -39,6 +46,19 @@ lazy_static! {
         hm
     };
 
+    /// The regex pattern for the release checksums asset
+    pub static ref CHECKSUMS_ASSET_RE: Regex = Regex::new(r"^SHA256SUMS(\.txt)?$").unwrap();
+    /// The regex pattern for the signature of the checksums asset
+    pub static ref SIGNATURE_ASSET_RE: Regex = Regex::new(r"^SHA256SUMS(\.txt)?\.minisig$").unwrap();
+    /// The regex pattern for the manifest with the mirrors of the release assets
+    pub static ref MIRRORS_ASSET_RE: Regex = Regex::new(r"^mirrors\.json$").unwrap();
+
+    /// The regex pattern for the version in the names of the archives
+    pub static ref ARCHIVE_VERSION_RE: Regex = Regex::new(r"\d+\.\d+\.\d+").unwrap();
+    /// The regex patterns for the name and version the mod defines in options.rpy
+    pub static ref CONFIG_NAME_RE: Regex = Regex::new(r#"(?m)^\s*define\s+config\.name\s*=\s*"([^"]*)""#).unwrap();
+    pub static ref CONFIG_VERSION_RE: Regex = Regex::new(r#"(?m)^\s*define\s+config\.version\s*=\s*"([^"]*)""#).unwrap();
+
     /// The headers we use to access GH API
     pub static ref HEADERS: HeaderMap = {
         let mut h = HeaderMap::new();
@@ -53,6 +73,13 @@ lazy_static! {
 
 /// The entry point
 fn main() {
+    // Run headless if we got a command, no display server needed
+    let args: Vec<String> = env::args().skip(1).collect();
+    if cli::is_cli_invocation(&args) {
+        utils::attach_parent_console();
+        process::exit(cli::run(&args));
+    }
+
     // This needs to be done first
     utils::disable_global_hotkeys();
     // Builds the app
@@ -0,0 +1,378 @@
+/// The module that implements installer settings
+/// Priority (highest first): command line flags, environment variables, settings file, defaults
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf}
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::errors::SettingsError;
+
+
+/// Default name of the settings file, it's looked up next to the executable
+pub const SETTINGS_FILE_NAME: &str = "jn-installer-settings.json";
+
+// Environment variables
+pub const ENV_SETTINGS_PATH: &str = "JN_INSTALLER_SETTINGS";
+pub const ENV_API_URL: &str = "JN_INSTALLER_API_URL";
+pub const ENV_ORG_NAME: &str = "JN_INSTALLER_ORG";
+pub const ENV_REPO_NAME: &str = "JN_INSTALLER_REPO";
+pub const ENV_ALLOW_INSECURE: &str = "JN_INSTALLER_INSECURE";
+pub const ENV_DOWNLOAD_WORKERS: &str = "JN_INSTALLER_DOWNLOAD_WORKERS";
+pub const ENV_MIRRORS: &str = "JN_INSTALLER_MIRRORS";
+pub const ENV_PROXY: &str = "JN_INSTALLER_PROXY";
+pub const ENV_NO_PROXY: &str = "JN_INSTALLER_NO_PROXY";
+pub const ENV_CONNECT_TIMEOUT: &str = "JN_INSTALLER_CONNECT_TIMEOUT";
+pub const ENV_READ_TIMEOUT: &str = "JN_INSTALLER_READ_TIMEOUT";
+pub const ENV_CA_BUNDLE: &str = "JN_INSTALLER_CA_BUNDLE";
+pub const ENV_GITHUB_TOKEN: &str = "JN_INSTALLER_GITHUB_TOKEN";
+pub const ENV_USE_CACHE: &str = "JN_INSTALLER_USE_CACHE";
+pub const ENV_CACHE_SIZE_LIMIT: &str = "JN_INSTALLER_CACHE_SIZE_LIMIT";
+pub const ENV_PRESERVE_TIMESTAMPS: &str = "JN_INSTALLER_PRESERVE_TIMESTAMPS";
+pub const ENV_MAX_ARCHIVE_ENTRIES: &str = "JN_INSTALLER_MAX_ARCHIVE_ENTRIES";
+pub const ENV_MAX_EXTRACTED_SIZE: &str = "JN_INSTALLER_MAX_EXTRACTED_SIZE";
+pub const ENV_MAX_COMPRESSION_RATIO: &str = "JN_INSTALLER_MAX_COMPRESSION_RATIO";
+pub const ENV_SYMLINK_POLICY: &str = "JN_INSTALLER_SYMLINK_POLICY";
+
+/// Sanity limit for the number of download workers
+pub const MAX_DOWNLOAD_WORKERS: usize = 16;
+/// Proxy URL schemes we support
+const PROXY_SCHEMES: [&str; 4] = ["http://", "https://", "socks5://", "socks5h://"];
+
+
+/// What to do with the symlinks in the DDLC dir and in the archives
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Stop the install before anything is changed
+    Refuse,
+    /// Use the symlinks, as long as they lead somewhere inside the DDLC dir
+    Follow,
+    /// Replace the symlinks with real dirs (or files in the archives)
+    Replace
+}
+
+impl SymlinkPolicy {
+    /// Returns the policy with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name.trim().to_lowercase().as_str() {
+            "refuse" => Some(Self::Refuse),
+            "follow" => Some(Self::Follow),
+            "replace" => Some(Self::Replace),
+            _ => None
+        };
+    }
+}
+
+impl Default for SymlinkPolicy {
+    fn default() -> Self {
+        return Self::Refuse;
+    }
+}
+
+
+/// Struct representing installer settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Base URL of a GitHub compatible API
+    pub api_base_url: String,
+    /// Owner of the repository with the releases
+    pub org_name: String,
+    /// Repository with the releases
+    pub repo_name: String,
+    /// Allows plain http for the API and the assets, meant for local stand-ins
+    pub allow_insecure: bool,
+    /// Minisign public key the release checksums must be signed with
+    /// NOTE: this is the trust root, so it's only taken from the command line, never from the file or the environment
+    #[serde(skip)]
+    pub public_key: String,
+    /// How many ranges of an asset are downloaded at once
+    pub download_workers: usize,
+    /// URL templates of mirrors to try when an asset can't be downloaded from GitHub,
+    /// "{tag}" and "{name}" are replaced with the release tag and the asset name
+    pub mirrors: Vec<String>,
+    /// Proxy for all requests, the system proxy (HTTP_PROXY, HTTPS_PROXY) is used if it's not set
+    pub proxy: Option<String>,
+    /// Comma-separated hosts that bypass the proxy, NO_PROXY is used if it's not set
+    pub no_proxy: Option<String>,
+    /// How many seconds we wait for a connection
+    pub connect_timeout: u64,
+    /// How many seconds we wait for data before a request fails
+    pub read_timeout: u64,
+    /// PEM file with extra CA certificates to trust, for proxies that intercept TLS
+    pub ca_bundle: Option<PathBuf>,
+    /// Token for the API, raises the rate limit
+    pub github_token: Option<String>,
+    /// Keeps the downloaded release assets, so they aren't downloaded again
+    pub use_cache: bool,
+    /// How many MiB the cached assets can take
+    pub cache_size_limit: u64,
+    /// Gives the extracted files the modification times they have in the archives
+    pub preserve_timestamps: bool,
+    /// How many entries an archive can have
+    pub max_archive_entries: usize,
+    /// How many MiB the files of an archive can take once extracted
+    pub max_extracted_size: u64,
+    /// How many times an extracted file can be bigger than its compressed data
+    pub max_compression_ratio: u64,
+    /// What to do with symlinks on the way to the files we install
+    pub symlink_policy: SymlinkPolicy
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        return Self {
+            api_base_url: crate::API_BASE_URL.to_string(),
+            org_name: crate::ORG_NAME.to_string(),
+            repo_name: crate::REPO_NAME.to_string(),
+            allow_insecure: false,
+            public_key: crate::static_data::RELEASE_PUBLIC_KEY.to_string(),
+            download_workers: 4,
+            mirrors: Vec::new(),
+            proxy: None,
+            no_proxy: None,
+            connect_timeout: 10,
+            read_timeout: 30,
+            ca_bundle: None,
+            github_token: None,
+            use_cache: false,
+            cache_size_limit: 2048,
+            preserve_timestamps: true,
+            max_archive_entries: 100000,
+            max_extracted_size: 16384,
+            max_compression_ratio: 100,
+            symlink_policy: SymlinkPolicy::default()
+        };
+    }
+}
+
+impl Settings {
+    /// Loads settings from the default settings file (if it exists) and the environment
+    /// This is "best-effort", a broken settings file is reported and ignored
+    pub fn load() -> Self {
+        let mut settings = match get_settings_path() {
+            Some(path) if path.is_file() => {
+                Self::from_file(&path).unwrap_or_else(
+                    |e| {
+                        eprintln!("Failed to load settings from '{}': {e}", path.display());
+                        Self::default()
+                    }
+                )
+            },
+            _ => Self::default()
+        };
+        settings.apply_env();
+
+        return settings;
+    }
+
+    /// Loads settings from the given file and the environment
+    pub fn load_from(path: &Path) -> Result<Self, SettingsError> {
+        let mut settings = Self::from_file(path)?;
+        settings.apply_env();
+
+        return Ok(settings);
+    }
+
+    /// Reads settings from a JSON file, missing fields get default values
+    fn from_file(path: &Path) -> Result<Self, SettingsError> {
+        let data = fs::read(path)?;
+        return Ok(serde_json::from_slice(&data)?);
+    }
+
+    /// Overrides settings with the values from environment variables
+    fn apply_env(&mut self) {
+        if let Ok(value) = env::var(ENV_API_URL) {
+            self.api_base_url = value;
+        }
+        if let Ok(value) = env::var(ENV_ORG_NAME) {
+            self.org_name = value;
+        }
+        if let Ok(value) = env::var(ENV_REPO_NAME) {
+            self.repo_name = value;
+        }
+        if let Ok(value) = env::var(ENV_ALLOW_INSECURE) {
+            self.allow_insecure = parse_flag(&value);
+        }
+        if let Ok(value) = env::var(ENV_DOWNLOAD_WORKERS) {
+            match value.trim().parse() {
+                Ok(value) => self.download_workers = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_DOWNLOAD_WORKERS}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_MIRRORS) {
+            self.mirrors = value.split(',')
+                .map(|url| url.trim())
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string())
+                .collect();
+        }
+        if let Ok(value) = env::var(ENV_PROXY) {
+            self.proxy = Some(value);
+        }
+        if let Ok(value) = env::var(ENV_NO_PROXY) {
+            self.no_proxy = Some(value);
+        }
+        if let Ok(value) = env::var(ENV_CONNECT_TIMEOUT) {
+            match value.trim().parse() {
+                Ok(value) => self.connect_timeout = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_CONNECT_TIMEOUT}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_READ_TIMEOUT) {
+            match value.trim().parse() {
+                Ok(value) => self.read_timeout = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_READ_TIMEOUT}: '{value}'")
+            };
+        }
+        if let Some(value) = env::var_os(ENV_CA_BUNDLE) {
+            self.ca_bundle = Some(PathBuf::from(value));
+        }
+        if let Ok(value) = env::var(ENV_GITHUB_TOKEN) {
+            self.github_token = Some(value);
+        }
+        if let Ok(value) = env::var(ENV_USE_CACHE) {
+            self.use_cache = parse_flag(&value);
+        }
+        if let Ok(value) = env::var(ENV_CACHE_SIZE_LIMIT) {
+            match value.trim().parse() {
+                Ok(value) => self.cache_size_limit = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_CACHE_SIZE_LIMIT}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_PRESERVE_TIMESTAMPS) {
+            self.preserve_timestamps = parse_flag(&value);
+        }
+        if let Ok(value) = env::var(ENV_MAX_ARCHIVE_ENTRIES) {
+            match value.trim().parse() {
+                Ok(value) => self.max_archive_entries = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_MAX_ARCHIVE_ENTRIES}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_MAX_EXTRACTED_SIZE) {
+            match value.trim().parse() {
+                Ok(value) => self.max_extracted_size = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_MAX_EXTRACTED_SIZE}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_MAX_COMPRESSION_RATIO) {
+            match value.trim().parse() {
+                Ok(value) => self.max_compression_ratio = value,
+                Err(_) => eprintln!("Ignoring invalid {ENV_MAX_COMPRESSION_RATIO}: '{value}'")
+            };
+        }
+        if let Ok(value) = env::var(ENV_SYMLINK_POLICY) {
+            match SymlinkPolicy::from_name(&value) {
+                Some(policy) => self.symlink_policy = policy,
+                None => eprintln!("Ignoring invalid {ENV_SYMLINK_POLICY}: '{value}'")
+            };
+        }
+    }
+
+    /// Returns whether the releases are verified with a key other than the built-in one
+    pub fn has_custom_public_key(&self) -> bool {
+        return self.public_key.trim() != crate::static_data::RELEASE_PUBLIC_KEY;
+    }
+
+    /// Returns the API URL of the repository with the releases
+    pub fn get_repo_api_url(&self) -> String {
+        return format!(
+            "{}/repos/{}/{}",
+            self.api_base_url.trim_end_matches('/'),
+            self.org_name,
+            self.repo_name
+        );
+    }
+
+    /// Returns the URLs of the given asset on the mirrors
+    /// Templates without "{name}" are treated as dirs the assets are in
+    pub fn get_mirror_urls(&self, tag: &str, asset_name: &str) -> Vec<String> {
+        return self.mirrors.iter()
+            .map(|template| {
+                let template = if template.contains("{name}") {
+                    template.clone()
+                }
+                else {
+                    format!("{}/{{name}}", template.trim_end_matches('/'))
+                };
+                template.replace("{tag}", tag).replace("{name}", asset_name)
+            })
+            .collect();
+    }
+
+    /// Checks if the given URL can be accessed with these settings
+    pub fn is_allowed_url(&self, url: &str) -> bool {
+        return url.starts_with("https://") || (self.allow_insecure && url.starts_with("http://"));
+    }
+
+    /// Checks if these settings can be used to install the game
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.org_name.is_empty() {
+            return Err(SettingsError::MissingValue("org_name"));
+        }
+        if self.repo_name.is_empty() {
+            return Err(SettingsError::MissingValue("repo_name"));
+        }
+        if self.public_key.is_empty() {
+            return Err(SettingsError::MissingValue("public_key"));
+        }
+        if self.download_workers == 0 || self.download_workers > MAX_DOWNLOAD_WORKERS {
+            return Err(SettingsError::InvalidValue("download_workers"));
+        }
+        if !self.is_allowed_url(&self.api_base_url) {
+            return Err(SettingsError::InsecureUrl(self.api_base_url.clone()));
+        }
+        if let Some(mirror) = self.mirrors.iter().find(|mirror| !self.is_allowed_url(mirror)) {
+            return Err(SettingsError::InsecureUrl(mirror.clone()));
+        }
+        if let Some(ref proxy) = self.proxy {
+            if !PROXY_SCHEMES.iter().any(|scheme| proxy.starts_with(scheme)) {
+                return Err(SettingsError::InvalidValue("proxy"));
+            }
+        }
+        if self.connect_timeout == 0 {
+            return Err(SettingsError::InvalidValue("connect_timeout"));
+        }
+        if self.read_timeout == 0 {
+            return Err(SettingsError::InvalidValue("read_timeout"));
+        }
+        if self.github_token.as_ref().map_or(false, |token| token.trim().is_empty()) {
+            return Err(SettingsError::MissingValue("github_token"));
+        }
+        if self.use_cache && self.cache_size_limit == 0 {
+            return Err(SettingsError::InvalidValue("cache_size_limit"));
+        }
+        if self.max_archive_entries == 0 {
+            return Err(SettingsError::InvalidValue("max_archive_entries"));
+        }
+        if self.max_extracted_size == 0 {
+            return Err(SettingsError::InvalidValue("max_extracted_size"));
+        }
+        if self.max_compression_ratio == 0 {
+            return Err(SettingsError::InvalidValue("max_compression_ratio"));
+        }
+
+        return Ok(());
+    }
+}
+
+
+/// Returns the path to the settings file
+/// The env var takes priority, otherwise we look next to the executable
+fn get_settings_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(ENV_SETTINGS_PATH) {
+        return Some(PathBuf::from(path));
+    }
+    let exe_path = env::current_exe().ok()?;
+
+    return Some(exe_path.parent()?.join(SETTINGS_FILE_NAME));
+}
+
+/// Parses a flag from a string like "1", "true", "yes"
+pub fn parse_flag(value: &str) -> bool {
+    return matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on");
+}
@@ -1,5 +1,8 @@
 /// The module with static data of our app
 
+// The minisign public key releases are signed with, see the README on how to sign them
+pub static RELEASE_PUBLIC_KEY: &'static str = "RWS5uFCQrYpqVMhYCNgBEQwWccoR2IdAObDWuldc7xGOsvh/EjJxsoK/";
+
 // Include the icon
 pub static APP_ICON_DATA: &'static [u8] = include_bytes!("static/icon.png");
 
@@ -2,8 +2,10 @@
 
 use std::{
     env,
-    path::PathBuf,
-    fs::read_dir
+    io::{self, Write},
+    path::{Path, PathBuf},
+    fs::{self, read_dir, File},
+    sync::atomic::{AtomicBool, Ordering}
 };
 
 use fltk::{
@@ -25,12 +27,129 @@ pub fn disable_global_hotkeys() {
 }
 
 
+/// Attaches the process to the console of the parent process (e.g. cmd or PowerShell),
+/// release builds on windows are GUI apps, so the CLI would print nowhere otherwise
+/// NOTE: when started from Explorer there's no console to attach to, the output is lost then
+#[cfg(windows)]
+pub fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // This fails if we already have a console (debug builds), which is fine
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// Attaches the process to the console of the parent process, other systems don't need this
+#[cfg(not(windows))]
+pub fn attach_parent_console() {}
+
+
+/// Set once the user has pressed Ctrl+C
+static IS_INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Checks if the user has pressed Ctrl+C since the handler has been set
+pub fn is_interrupted() -> bool {
+    return IS_INTERRUPTED.load(Ordering::SeqCst);
+}
+
+/// Makes Ctrl+C only set the interrupted flag, so the install can be rolled back properly
+/// Pressing it again kills the process like it normally would
+#[cfg(unix)]
+pub fn set_interrupt_handler() {
+    const SIGINT: i32 = 2;
+    const SIG_DFL: usize = 0;
+
+    extern "C" {
+        fn signal(signum: i32, handler: usize) -> usize;
+    }
+
+    extern "C" fn handle_interrupt(_signum: i32) {
+        IS_INTERRUPTED.store(true, Ordering::SeqCst);
+        // Only async-signal-safe calls are allowed here, signal is one of them
+        unsafe {
+            signal(SIGINT, SIG_DFL);
+        }
+    }
+
+    unsafe {
+        signal(SIGINT, handle_interrupt as extern "C" fn(i32) as usize);
+    }
+}
+
+/// Makes Ctrl+C only set the interrupted flag, so the install can be rolled back properly
+/// Pressing it again kills the process like it normally would
+#[cfg(windows)]
+pub fn set_interrupt_handler() {
+    const CTRL_C_EVENT: u32 = 0;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<extern "system" fn(u32) -> i32>, add: i32) -> i32;
+    }
+
+    // Returning 0 passes the event to the default handler, which exits the process
+    extern "system" fn handle_ctrl_event(ctrl_type: u32) -> i32 {
+        if ctrl_type != CTRL_C_EVENT || IS_INTERRUPTED.swap(true, Ordering::SeqCst) {
+            return 0;
+        }
+        return 1;
+    }
+
+    unsafe {
+        SetConsoleCtrlHandler(Some(handle_ctrl_event), 1);
+    }
+}
+
+/// Ctrl+C can't be handled on other systems, the process is just killed
+#[cfg(not(any(unix, windows)))]
+pub fn set_interrupt_handler() {}
+
+
 /// Returns current working dir
 pub fn get_cwd() -> PathBuf {
     let cwd = env::current_dir();
     return cwd.ok().unwrap_or_default();
 }
 
+/// Returns the dir the installer keeps its data in between runs
+pub fn get_cache_dir() -> PathBuf {
+    let base_dir = match env::consts::OS {
+        "windows" => env::var_os("LOCALAPPDATA").map(PathBuf::from),
+        "macos" => env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches")),
+        // XDG spec says relative paths must be ignored
+        _ => {
+            env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        }
+    };
+    return base_dir.unwrap_or_else(env::temp_dir).join("jn-installer");
+}
+
+/// Writes the data into a temporary file next to the given path and then moves it in place,
+/// so the file is never left half-written
+pub fn write_file_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    return fs::rename(&temp_path, path);
+}
+
 /// Checks if the given path is a valid DDLC directory
 pub fn is_valid_ddlc_dir(path: &PathBuf) -> bool {
     const TOTAL_CONDITIONS: u16 = 5;